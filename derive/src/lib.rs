@@ -4,7 +4,9 @@
 use proc_macro::TokenStream;
 
 use quote::quote;
-use syn::{parse_macro_input, ItemFn};
+use syn::meta::ParseNestedMeta;
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, ItemFn, LitStr, Token};
 
 /// Main function attribute for a FastEdge application.
 ///
@@ -21,47 +23,141 @@ use syn::{parse_macro_input, ItemFn};
 /// fn main(req: Request<Body>) -> Result<Response<Body>> {
 ///     Response::builder().status(StatusCode::OK).body(Body::empty())
 /// }
+/// ```
+///
+/// ## Errors
+///
+/// An error that converts into `Response<Body>`, such as `fastedge::http::HandlerError` or
+/// `fastedge::Error`, is sent as that response, keeping its status. With the `anyhow` feature of
+/// `fastedge` an `anyhow::Error` is sent as `fastedge::http::anyhow_response` builds it. Any other
+/// error is a `500 Internal Server Error` with the error message as body.
+///
+/// ## Options
+///
+/// - `default_headers("name: value", ...)` — headers added to every response, including the
+///   macro's own error responses, unless the handler has already set them:
+///
+/// ```rust,no_run
+/// # use anyhow::Result;
+/// # use fastedge::http::{Request, Response, StatusCode};
+/// # use fastedge::body::Body;
+/// #[fastedge::http(default_headers("x-content-type-options: nosniff", "server: fastedge"))]
+/// fn main(req: Request<Body>) -> Result<Response<Body>> {
+///     Response::builder().status(StatusCode::OK).body(Body::empty())
+/// }
+/// ```
+///
+/// - `auto_compress` — compress textual responses with the encoding negotiated from the request
+///   `Accept-Encoding` header, requires the `compression` feature of `fastedge`.
+/// - `error_format = "json"` — report `500 Internal Server Error` responses for other errors as
+///   `{"error": "..."}` with `Content-Type: application/json` instead of `text/plain; charset=utf-8`.
+/// - `trace_sizes` — emit request and response body sizes as a `tracing` event after every
+///   invocation, see `fastedge::http::trace::record_sizes`.
 #[proc_macro_attribute]
-pub fn http(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn http(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut options = Options::default();
+    let parser = syn::meta::parser(|meta| options.parse(meta));
+    parse_macro_input!(attr with parser);
+
     let func = parse_macro_input!(item as ItemFn);
     let func_name = &func.sig.ident;
 
+    let default_headers = if options.default_headers.is_empty() {
+        quote!()
+    } else {
+        let (names, values): (Vec<_>, Vec<_>) = options.default_headers.into_iter().unzip();
+        quote!(
+            let res = ::fastedge::http::with_default_headers(res, &[#((#names, #values)),*]);
+        )
+    };
+
+    let (accept_encoding, compress) = if options.auto_compress {
+        (
+            quote!(
+                let accept_encoding = req
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("accept-encoding"))
+                    .and_then(|(_, value)| ::fastedge::http::HeaderValue::from_str(value).ok());
+            ),
+            quote!(
+                let res = ::fastedge::http::compression::compress(res, accept_encoding.as_ref());
+            ),
+        )
+    } else {
+        (quote!(), quote!())
+    };
+
+    let (request_size, trace_sizes) = if options.trace_sizes {
+        (
+            quote!(
+                let request_bytes = req.body.as_ref().map_or(0, |body| body.len());
+            ),
+            quote!(
+                ::fastedge::http::trace::record_sizes(request_bytes, res.body().len());
+            ),
+        )
+    } else {
+        (quote!(), quote!())
+    };
+
+    let internal_error = match options.error_format {
+        ErrorFormat::Text => quote!(
+            #[inline(always)]
+            fn internal_error(body: &str) -> ::fastedge::http::Response<::fastedge::body::Body> {
+                ::fastedge::http::internal_error(body)
+            }
+        ),
+        ErrorFormat::Json => quote!(
+            #[inline(always)]
+            fn internal_error(body: &str) -> ::fastedge::http::Response<::fastedge::body::Body> {
+                ::fastedge::http::json_error_response(
+                    ::fastedge::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    body,
+                )
+            }
+        ),
+    };
+
     quote!(
         use fastedge::http_handler::Guest;
         struct Component;
 
-        #[inline(always)]
-        fn internal_error(body: &str) -> ::fastedge::http_handler::Response {
-            ::fastedge::http_handler::Response {
-                status: ::fastedge::http::StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
-                headers: Some(vec![]),
-                body: Some(body.as_bytes().to_vec()),
-            }
-        }
+        #internal_error
 
+        // unmangled `main` would clash with the test harness entry point
         #[inline(always)]
-        #[no_mangle]
+        #[cfg_attr(not(test), no_mangle)]
         #func
 
         impl Guest for Component {
             #[no_mangle]
             fn process(req: ::fastedge::http_handler::Request) -> ::fastedge::http_handler::Response {
+                #accept_encoding
+                #request_size
 
-                let Ok(request) = req.try_into() else {
-                    return internal_error("http request decode error")
-                };
-
-                let res = match #func_name(request) {
-                    Ok(res) => res,
-                    Err(error) => {
-                        return internal_error(error.to_string().as_str());
-                    }
+                let res = match req.try_into() {
+                    Ok(request) => match #func_name(request) {
+                        Ok(res) => res,
+                        Err(error) => {
+                            // errors that can produce a response keep their status
+                            #[allow(unused_imports)]
+                            use ::fastedge::http::handler_error_kind::{
+                                AnyhowKind as _, DisplayKind as _, ResponseKind as _,
+                            };
+                            (&error)
+                                .fastedge_error_kind()
+                                .response(error)
+                                .unwrap_or_else(|message| internal_error(&message))
+                        }
+                    },
+                    Err(_) => internal_error("http request decode error"),
                 };
+                #default_headers
+                #compress
+                #trace_sizes
 
-                let Ok(response) = ::fastedge::http_handler::Response::try_from(res) else {
-                    return internal_error("http response encode error")
-                };
-                response
+                ::fastedge::http_handler::Response::from(res)
             }
         }
 
@@ -70,3 +166,74 @@ pub fn http(_attr: TokenStream, item: TokenStream) -> TokenStream {
 
     ).into()
 }
+
+/// `#[fastedge::http(...)]` attribute options
+#[derive(Default)]
+struct Options {
+    default_headers: Vec<(String, String)>,
+    auto_compress: bool,
+    error_format: ErrorFormat,
+    trace_sizes: bool,
+}
+
+/// Body format of the generated `500 Internal Server Error` response
+#[derive(Default)]
+enum ErrorFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl Options {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("default_headers") {
+            let content;
+            syn::parenthesized!(content in meta.input);
+            for header in Punctuated::<LitStr, Token![,]>::parse_terminated(&content)? {
+                let value = header.value();
+                let Some((name, value)) = value.split_once(':') else {
+                    return Err(syn::Error::new(header.span(), "expected `name: value`"));
+                };
+                let (name, value) = (name.trim(), value.trim());
+                if name.is_empty() || !name.bytes().all(is_token) {
+                    return Err(syn::Error::new(header.span(), "invalid header name"));
+                }
+                if !value
+                    .bytes()
+                    .all(|b| b == b'\t' || (b' '..=b'~').contains(&b))
+                {
+                    return Err(syn::Error::new(header.span(), "invalid header value"));
+                }
+                self.default_headers
+                    .push((name.to_ascii_lowercase(), value.to_string()));
+            }
+            Ok(())
+        } else if meta.path.is_ident("auto_compress") {
+            self.auto_compress = true;
+            Ok(())
+        } else if meta.path.is_ident("trace_sizes") {
+            self.trace_sizes = true;
+            Ok(())
+        } else if meta.path.is_ident("error_format") {
+            let format = meta.value()?.parse::<LitStr>()?;
+            self.error_format = match format.value().as_str() {
+                "text" => ErrorFormat::Text,
+                "json" => ErrorFormat::Json,
+                _ => {
+                    return Err(syn::Error::new(
+                        format.span(),
+                        "expected \"text\" or \"json\"",
+                    ))
+                }
+            };
+            Ok(())
+        } else {
+            Err(meta.error("unsupported fastedge::http option"))
+        }
+    }
+}
+
+/// RFC 9110 `tchar`
+fn is_token(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
@@ -1,5 +1,6 @@
-use anyhow::{anyhow, Error, Result};
+use anyhow::{anyhow, Result};
 use fastedge::body::Body;
+use fastedge::encoding::url;
 use fastedge::http::{Method, Request, Response, StatusCode};
 
 #[allow(dead_code)]
@@ -10,23 +11,21 @@ fn main(req: Request<Body>) -> Result<Response<Body>> {
         .uri
         .query()
         .ok_or(anyhow!("missing uri query parameter"))?;
-    let params = querystring::querify(query);
-    let url = params
-        .iter()
-        .find(|(k, _)| k == &"url")
+    let url = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("url="))
         .ok_or(anyhow!("missing url parameter"))?;
-    let url = urlencoding::decode(url.1)?.to_string();
+    let url = url::decode(url);
     println!("url = {:?}", url);
     let request = Request::builder().uri(url).method(Method::GET).body(body)?;
 
-    let response = fastedge::send_request(request).map_err(Error::msg)?;
+    let response = fastedge::send_request(request)?;
 
-    Response::builder()
+    Ok(Response::builder()
         .status(StatusCode::OK)
-        .body(Body::from(format!(
+        .body(Body::from_fmt(format_args!(
             "len = {}, content-type = {:?}",
             response.body().len(),
             response.headers().get("Content-Type")
-        )))
-        .map_err(Error::msg)
+        )))?)
 }
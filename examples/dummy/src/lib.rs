@@ -10,3 +10,18 @@ fn main(_req: Request<Body>) -> Result<Response<Body>> {
         .body(Body::empty())?;
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use fastedge::testing::{process, TestRequest};
+
+    use super::*;
+
+    #[test]
+    fn empty_ok() {
+        let res = process::<Component>(TestRequest::get("http://localhost/").build());
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.body().is_empty());
+        assert_eq!(res.headers()["content-length"], "0");
+    }
+}
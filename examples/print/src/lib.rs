@@ -26,3 +26,28 @@ fn main(req: Request<Body>) -> Result<Response<Body>> {
         .body(Body::from(body))?;
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use fastedge::testing::{process, TestRequest};
+
+    use super::*;
+
+    #[test]
+    fn prints_request() {
+        let req = TestRequest::post("http://localhost/path?q=1")
+            .header("x-test", "value")
+            .body("ignored")
+            .build();
+        let res = process::<Component>(req);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            std::str::from_utf8(res.body()).unwrap(),
+            "Method: POST\nURL: http://localhost/path?q=1\nHeaders:\n    x-test: value"
+        );
+        assert_eq!(
+            res.headers()["content-length"],
+            res.body().len().to_string()
+        );
+    }
+}
@@ -11,10 +11,11 @@
 // if file from S3 cannot be recognised as valid image, it is passed to caller as is
 
 const DEFAULT_OPACITY: f32 = 1.0; // to use non-default opacity, specify OPACITY in 0-1.0 range in app env
+const MAX_IMAGE_SIZE: usize = 16 * 1024 * 1024; // larger S3 objects are rejected instead of decoded
 
 use fastedge::{
     body::Body,
-    http::{header, Error, Method, Request, Response, StatusCode},
+    http::{header, proxy, sanitize_path, HandlerError, Method, Request, Response, StatusCode},
 };
 use image::*;
 use rusty_s3::{Bucket, Credentials, S3Action, UrlStyle};
@@ -22,7 +23,7 @@ use std::{env, io::Cursor, time::Duration};
 use url::Url;
 
 #[fastedge::http]
-fn main(req: Request<Body>) -> Result<Response<Body>, Error> {
+fn main(req: Request<Body>) -> Result<Response<Body>, HandlerError> {
     // embed watermark file - file must be present during compilation
     let wm_buf = include_bytes!("sample.png");
 
@@ -33,27 +34,32 @@ fn main(req: Request<Body>) -> Result<Response<Body>, Error> {
 
         // Deny anything else.
         _ => {
-            return Response::builder()
+            return Ok(Response::builder()
                 .status(StatusCode::METHOD_NOT_ALLOWED)
                 .header(header::ALLOW, "GET, HEAD")
-                .body(Body::from("This method is not allowed\n"));
+                .body(Body::from("This method is not allowed\n"))?);
         }
     };
 
     // get filename from URL with has format <scheme>://<host>/<filename>
-    let filename = req.uri().path().trim_start_matches('/');
-    if filename.is_empty() {
-        return Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(Body::from("Malformed request - filename expected\n"));
-    }
+    // the path is normalized so it cannot escape the bucket prefix with `..`
+    let filename = match sanitize_path(req.uri().path()) {
+        Ok(filename) => filename,
+        Err(_) => {
+            return Err(HandlerError::new(
+                StatusCode::BAD_REQUEST,
+                "Malformed request - filename expected\n",
+            ));
+        }
+    };
 
     // construct S3 signed URL
-    let (signed_url, host) = match sign_s3(filename) {
+    let (signed_url, host) = match sign_s3(&filename) {
         Err(_) => {
-            return Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::from("App misconfigured\n"))
+            return Err(HandlerError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "App misconfigured\n",
+            ))
         }
         Ok((u, h)) => (u, h),
     };
@@ -63,25 +69,20 @@ fn main(req: Request<Body>) -> Result<Response<Body>, Error> {
         .method(Method::GET)
         .uri(signed_url.as_str())
         .header("Host", host)
-        .body(Body::empty())
-        .expect("error building the request");
-    let rsp = match fastedge::send_request(s3_req) {
-        Err(_) => {
-            return Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::empty())
-        }
-        Ok(r) => r,
+        .body(Body::empty())?;
+    let options = fastedge::RequestOptions {
+        max_response_bytes: Some(MAX_IMAGE_SIZE),
+        ..Default::default()
     };
+    // a failed S3 call is answered with the status from `fastedge::Error::status_hint`
+    let rsp = fastedge::send_request_with_options(s3_req, &options)?;
 
     // if response is not 200, just forward it to the caller
     let (parts, body) = rsp.into_parts();
     if parts.status != StatusCode::OK {
-        return Ok(Response::from_parts(parts, body));
+        return Ok(proxy::passthrough(Response::from_parts(parts, body)));
         // if you don't want to expose S3 error to the caller, just use
-        // return Response::builder()
-        //     .status(StatusCode::INTERNAL_SERVER_ERROR)
-        //     .body(Body::empty())
+        // return Err(HandlerError::new(StatusCode::BAD_GATEWAY, "S3 error\n"));
     }
 
     // load response as image
@@ -109,9 +110,10 @@ fn main(req: Request<Body>) -> Result<Response<Body>, Error> {
         Err(_e) =>
         // should never happen
         {
-            return Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::from("Invalid watermark format\n"))
+            return Err(HandlerError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Invalid watermark format\n",
+            ))
         }
     };
 
@@ -120,16 +122,19 @@ fn main(req: Request<Body>) -> Result<Response<Body>, Error> {
         None => DEFAULT_OPACITY,
         Some(l) => match l.parse::<f32>() {
             Err(_) => {
-                return Response::builder() // opacity is not a number
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(Body::from("Invalid opacity value\n"));
+                // opacity is not a number
+                return Err(HandlerError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Invalid opacity value\n",
+                ));
             }
             Ok(v) if !(0.0..=1.0).contains(&v) =>
             // opacity is not in 0-1.0 range
             {
-                return Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(Body::from("Invalid opacity value\n"))
+                return Err(HandlerError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Invalid opacity value\n",
+                ))
             }
             Ok(v) => v,
         },
@@ -146,10 +151,10 @@ fn main(req: Request<Body>) -> Result<Response<Body>, Error> {
     let mut c = Cursor::new(&mut out);
     let _ = result.write_to(&mut c, out_format);
 
-    Response::builder()
+    Ok(Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, out_format.to_mime_type())
-        .body(Body::from(out))
+        .body(Body::from(out))?)
 }
 
 // Apply watermark using alpha blending
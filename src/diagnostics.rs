@@ -0,0 +1,60 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+//! Diagnostics are emitted as `tracing` events, the runtime collects them together with the app
+//! logs.
+use std::any::Any;
+use std::panic::{self, Location};
+
+/// Install a panic hook that emits the panic message and location as a `tracing` error event.
+///
+/// The previously installed hook still runs afterwards, so the default stderr report is kept.
+/// Call it once at the start of the handler.
+pub fn install_panic_hook() {
+    let previous = panic::take_hook();
+    // the hook argument is not named, `PanicHookInfo` only exists since Rust 1.81
+    panic::set_hook(Box::new(move |info| {
+        report_panic(info.payload(), info.location());
+        previous(info);
+    }));
+}
+
+fn report_panic(payload: &(dyn Any + Send), location: Option<&Location<'_>>) {
+    let message = panic_message(payload);
+    let location = location
+        .map(|location| location.to_string())
+        .unwrap_or_default();
+    tracing::error!(message, location, "panic");
+}
+
+/// panic payload as text, `panic!` payloads are either `&str` or `String`
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>")
+}
+
+/// Emit an error with its full cause chain as a `tracing` error event, enabled with the `anyhow`
+/// feature
+#[cfg(feature = "anyhow")]
+pub fn report_error(error: &anyhow::Error) {
+    let chain = format!("{:#}", error);
+    tracing::error!(error = %error, chain, "handler error");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_from_payload() {
+        let payload = panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "static");
+        let payload = panic::catch_unwind(|| panic!("formatted {}", 1)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "formatted 1");
+        let payload = panic::catch_unwind(|| panic::panic_any(1)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "Box<dyn Any>");
+    }
+}
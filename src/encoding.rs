@@ -0,0 +1,7 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+/// Hexadecimal encoding
+pub mod hex;
+/// URL percent-encoding
+pub mod url;
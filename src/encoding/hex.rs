@@ -0,0 +1,66 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+/// Error returned by [`decode`]
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Input has an odd number of digits
+    #[error("odd number of hex digits")]
+    OddLength,
+    /// Character at the given byte offset is not a hex digit
+    #[error("invalid hex character at offset {0}")]
+    InvalidCharacter(usize),
+}
+
+/// Lowercase hex encoding
+pub fn encode(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(DIGITS[usize::from(byte >> 4)] as char);
+        hex.push(DIGITS[usize::from(byte & 0x0f)] as char);
+    }
+    hex
+}
+
+/// Decode hex digits of either case
+pub fn decode(hex: &str) -> Result<Vec<u8>, DecodeError> {
+    let digits = hex.as_bytes();
+    if digits.len() % 2 == 1 {
+        return Err(DecodeError::OddLength);
+    }
+    let digit = |offset: usize| {
+        let value = match digits[offset] {
+            c @ b'0'..=b'9' => c - b'0',
+            c @ b'a'..=b'f' => c - b'a' + 10,
+            c @ b'A'..=b'F' => c - b'A' + 10,
+            _ => return Err(DecodeError::InvalidCharacter(offset)),
+        };
+        Ok(value)
+    };
+    (0..digits.len())
+        .step_by(2)
+        .map(|offset| Ok(digit(offset)? << 4 | digit(offset + 1)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(encode(&[0x00, 0x0f, 0xab, 0xff]), "000fabff");
+        assert_eq!(decode("000fabff"), Ok(vec![0x00, 0x0f, 0xab, 0xff]));
+        assert_eq!(decode("ABff"), Ok(vec![0xab, 0xff]));
+        assert_eq!(encode(&[]), "");
+        assert_eq!(decode(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(decode("abc"), Err(DecodeError::OddLength));
+        assert_eq!(decode("0g"), Err(DecodeError::InvalidCharacter(1)));
+        assert_eq!(decode("00 0"), Err(DecodeError::InvalidCharacter(2)));
+    }
+}
@@ -0,0 +1,85 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+//! Percent-encoding (RFC 3986). Escapes are written in uppercase hex; text is encoded as UTF-8.
+
+/// Percent-encode a URL path, every byte except RFC 3986 unreserved characters and `/` is escaped
+pub fn encode(value: &str) -> String {
+    encode_except(value, b"-._~/")
+}
+
+/// Percent-encode a query name or value, or a single path segment, every byte except RFC 3986
+/// unreserved characters is escaped
+pub fn encode_query_component(value: &str) -> String {
+    encode_except(value, b"-._~")
+}
+
+/// Decode `%XX` escapes, invalid escapes are kept as is and invalid UTF-8 is replaced
+pub fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// [`decode`] a `application/x-www-form-urlencoded` name or value, where `+` stands for a space
+pub fn decode_form_component(value: &str) -> String {
+    decode(&value.replace('+', " "))
+}
+
+fn encode_except(value: &str, keep: &[u8]) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || keep.contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        assert_eq!(encode("/a b/c?d=é"), "/a%20b/c%3Fd%3D%C3%A9");
+        assert_eq!(encode_query_component("a/b&c=d~"), "a%2Fb%26c%3Dd~");
+    }
+
+    #[test]
+    fn decoding() {
+        assert_eq!(decode("a%20b%2Fc%C3%A9"), "a b/cé");
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz%4"), "%zz%4");
+        assert_eq!(decode("%FF"), "\u{fffd}");
+        assert_eq!(decode("a+b"), "a+b");
+        assert_eq!(decode_form_component("a+b%2B"), "a b+");
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = "naïve / query&value=1+2";
+        assert_eq!(decode(&encode(value)), value);
+        assert_eq!(decode(&encode_query_component(value)), value);
+    }
+}
@@ -0,0 +1,43 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::collections::HashMap;
+
+/// All environment variables configured for the app, variables that are not valid UTF-8 are
+/// skipped.
+///
+/// App secrets are configured as environment variables too, never return the result of this
+/// function in a response.
+pub fn all() -> HashMap<String, String> {
+    std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
+}
+
+/// Environment variables whose name starts with `prefix`, keyed by the rest of the name.
+///
+/// For example `with_prefix("S3_")` returns `S3_BUCKET` as `BUCKET`. The same care about secrets
+/// as for [`all`] applies.
+pub fn with_prefix(prefix: &str) -> HashMap<String, String> {
+    all()
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.strip_prefix(prefix)?.to_string(), value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixed_variables() {
+        std::env::set_var("FASTEDGE_ENV_TEST_BUCKET", "assets");
+        assert_eq!(
+            all().get("FASTEDGE_ENV_TEST_BUCKET").map(String::as_str),
+            Some("assets")
+        );
+        let vars = with_prefix("FASTEDGE_ENV_TEST_");
+        assert_eq!(vars.get("BUCKET").map(String::as_str), Some("assets"));
+        assert!(!vars.contains_key("FASTEDGE_ENV_TEST_BUCKET"));
+    }
+}
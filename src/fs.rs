@@ -0,0 +1,47 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+//! Files are resolved through the WASI preopened directories the runtime mounts for the
+//! application, so only paths inside a mounted directory are readable and anything else fails
+//! with an [`io::Error`](std::io::Error). Assets loaded this way don't need to be embedded with
+//! `include_bytes!`, which keeps the application binary small.
+//!
+//! The SDK does not preopen anything itself: which directories are mounted, and at which guest
+//! paths, is decided by the host. An application without mounted directories can't read any
+//! file. When running a component locally with Wasmtime, a directory is mounted with
+//! `--dir <host-dir>::<guest-path>`, and paths passed here are guest paths.
+use std::io;
+use std::path::Path;
+
+/// Read the entire contents of a file
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    std::fs::read(path)
+}
+
+/// Read the entire contents of a UTF-8 text file
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    std::fs::read_to_string(path)
+}
+
+/// Check whether a file exists in the mounted directories
+pub fn exists(path: impl AsRef<Path>) -> bool {
+    path.as_ref().is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_files() {
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        assert!(exists(&manifest));
+        assert!(read_to_string(&manifest).unwrap().contains("[package]"));
+        assert_eq!(read(&manifest).unwrap(), std::fs::read(&manifest).unwrap());
+
+        let missing = manifest.with_file_name("missing.toml");
+        assert!(!exists(&missing));
+        assert_eq!(read(&missing).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert!(!exists(manifest.parent().unwrap()));
+    }
+}
@@ -0,0 +1,95 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+pub use ::http::*;
+
+/// `Accept-Language` negotiation
+pub mod accept_language;
+/// Request body size limit
+pub mod body_limit;
+/// `Cache-Control` header builder and parser
+pub mod cache_control;
+/// `Accept-Encoding` negotiation and response compression
+pub mod compression;
+/// Cross-origin resource sharing policy
+pub mod cors;
+/// `Content-Security-Policy` builder
+pub mod csp;
+/// `ETag` computation and matching
+pub mod etag;
+/// RFC 7239 `Forwarded` header parsing
+pub mod forwarded;
+/// Header values from untrusted input
+pub mod header_value;
+/// `Idempotency-Key` header support
+pub mod idempotency;
+/// Method tunneling through `POST`
+pub mod method_override;
+/// RFC 7807 problem details responses
+#[cfg(feature = "json")]
+pub mod problem;
+/// Helpers for proxying requests to backends and forwarding their responses
+pub mod proxy;
+/// Client details from proxy headers
+pub mod proxy_headers;
+/// `Range` header parsing
+pub mod range;
+/// HTTP to HTTPS redirects
+pub mod redirect_https;
+/// `Retry-After` header parsing
+pub mod retry_after;
+/// Request canonicalization for signing and signed URL verification
+#[cfg(feature = "crypto")]
+pub mod sign;
+/// Server-Sent Events parsing
+pub mod sse;
+/// Status codes re-exported from [`http::status`] with family checks
+pub mod status;
+/// Timing instrumentation and W3C trace context propagation
+pub mod trace;
+/// URI types re-exported from [`http::uri`] with a query string builder
+pub mod uri;
+/// JSON request validation
+#[cfg(feature = "json")]
+pub mod validate;
+/// `Vary` header management
+pub mod vary;
+
+mod builder_ext;
+mod default_headers;
+mod error_response;
+mod handler_error;
+mod head;
+mod header_map_ext;
+pub(crate) mod headers;
+#[cfg(feature = "json")]
+mod json;
+mod method_ext;
+mod parse_body;
+mod replace_body;
+mod request_ext;
+mod response_ext;
+mod sanitize_path;
+mod serve;
+
+pub use builder_ext::ResponseBuilderExt;
+pub use compression::negotiate_encoding;
+pub use default_headers::with_default_headers;
+pub use error_response::{error_response, internal_error, json_error_response, status_response};
+#[cfg(feature = "anyhow")]
+pub use handler_error::anyhow_response;
+#[doc(hidden)]
+pub use handler_error::kind as handler_error_kind;
+pub use handler_error::HandlerError;
+pub use head::strip_body_for_head;
+pub use header_map_ext::HeaderMapExt;
+#[cfg(feature = "json")]
+pub use json::{parse_json_body, JsonBodyError};
+pub use method_ext::{method_is_idempotent, method_is_safe};
+pub use parse_body::{parse_body, BodyData};
+pub use replace_body::replace_body;
+pub(crate) use request_ext::NoBody;
+pub use request_ext::RequestExt;
+pub use response_ext::ResponseExt;
+pub use sanitize_path::{sanitize_path, PathError};
+pub use serve::serve_bytes;
@@ -0,0 +1,116 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, Request};
+
+use crate::body::Body;
+
+/// Best supported language for the request `Accept-Language` header.
+///
+/// Language ranges are tried by descending q-value. A range matches a supported tag exactly,
+/// then with subtags removed from its end (`en-US` → `en`), then any supported tag with the same
+/// primary language. `*` picks the first supported tag that is not excluded with `q=0`. Returns
+/// `None` without the header or when nothing matches.
+pub fn best_match(req: &Request<Body>, supported: &[&str]) -> Option<String> {
+    let accept_language = req.headers().get(header::ACCEPT_LANGUAGE)?.to_str().ok()?;
+
+    let mut ranges = vec![];
+    let mut excluded = vec![];
+    for item in accept_language.split(',') {
+        let mut params = item.split(';');
+        let range = params.next().unwrap_or_default().trim();
+        if range.is_empty() {
+            continue;
+        }
+        let q = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if q <= 0.0 {
+            excluded.push(range);
+        } else {
+            ranges.push((range, q));
+        }
+    }
+    // stable sort keeps the client order between equal weights
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let find = |tag: &str| {
+        supported
+            .iter()
+            .find(|candidate| candidate.eq_ignore_ascii_case(tag))
+    };
+    for (range, _) in ranges {
+        if range == "*" {
+            let found = supported.iter().find(|candidate| {
+                !excluded
+                    .iter()
+                    .any(|excluded| excluded.eq_ignore_ascii_case(candidate))
+            });
+            if let Some(found) = found {
+                return Some(found.to_string());
+            }
+            continue;
+        }
+
+        let mut tag = range;
+        loop {
+            if let Some(found) = find(tag) {
+                return Some(found.to_string());
+            }
+            match tag.rsplit_once('-') {
+                Some((prefix, _)) => tag = prefix,
+                None => break,
+            }
+        }
+        let found = supported.iter().find(|candidate| {
+            let primary = candidate.split('-').next().unwrap_or_default();
+            primary.eq_ignore_ascii_case(tag)
+        });
+        if let Some(found) = found {
+            return Some(found.to_string());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUPPORTED: &[&str] = &["en-US", "fr", "de-DE", "pt-BR"];
+
+    fn best(accept_language: &str) -> Option<String> {
+        let req = Request::builder()
+            .header(header::ACCEPT_LANGUAGE, accept_language)
+            .body(Body::empty())
+            .unwrap();
+        best_match(&req, SUPPORTED)
+    }
+
+    #[test]
+    fn exact_and_prefix_matches() {
+        assert_eq!(best("en-us").as_deref(), Some("en-US"));
+        assert_eq!(best("fr-CA").as_deref(), Some("fr"));
+        assert_eq!(best("de").as_deref(), Some("de-DE"));
+        assert_eq!(best("ja"), None);
+    }
+
+    #[test]
+    fn q_values() {
+        assert_eq!(best("fr;q=0.5, de;q=0.8").as_deref(), Some("de-DE"));
+        assert_eq!(best("ja, pt;q=0.1, fr;q=0.1").as_deref(), Some("pt-BR"));
+        assert_eq!(best("fr;q=0").as_deref(), None);
+    }
+
+    #[test]
+    fn wildcard() {
+        assert_eq!(best("ja, *;q=0.1").as_deref(), Some("en-US"));
+        assert_eq!(best("*, en-US;q=0").as_deref(), Some("fr"));
+    }
+
+    #[test]
+    fn missing_header() {
+        assert_eq!(best_match(&Request::new(Body::empty()), SUPPORTED), None);
+    }
+}
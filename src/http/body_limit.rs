@@ -0,0 +1,43 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{Request, StatusCode};
+
+use super::HandlerError;
+use crate::body::Body;
+
+/// Reject request bodies larger than `max` bytes with a `413 Payload Too Large` error, turn it
+/// into the response with [`HandlerError::into_response`] or `?` it from a handler returning
+/// `Result<_, HandlerError>`
+pub fn check(req: &Request<Body>, max: usize) -> Result<(), HandlerError> {
+    if req.body().len() > max {
+        return Err(HandlerError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("request body exceeds {} bytes\n", max),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn within_limit() {
+        let req = Request::new(Body::from("0123456789"));
+        assert_eq!(check(&req, 10), Ok(()));
+        assert_eq!(check(&Request::new(Body::empty()), 0), Ok(()));
+    }
+
+    #[test]
+    fn over_limit() {
+        let req = Request::new(Body::from("0123456789"));
+        let error = check(&req, 9).unwrap_err();
+        assert_eq!(error.status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let res = error.into_response();
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(&res.body()[..], b"request body exceeds 9 bytes\n");
+    }
+}
@@ -0,0 +1,90 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, response, Response};
+
+use crate::body::Body;
+use crate::Error;
+
+/// Extension methods for [`response::Builder`] setting body and `Content-Type` in one call
+pub trait ResponseBuilderExt {
+    /// Finish the response with a `text/plain; charset=utf-8` body
+    fn text_body(self, text: &str) -> Result<Response<Body>, Error>;
+
+    /// Finish the response with the value serialized as an `application/json` body
+    #[cfg(feature = "json")]
+    fn json_body<T: serde::Serialize + ?Sized>(self, value: &T) -> Result<Response<Body>, Error>;
+}
+
+impl ResponseBuilderExt for response::Builder {
+    fn text_body(self, text: &str) -> Result<Response<Body>, Error> {
+        finish(self, Body::from(text.to_string()))
+    }
+
+    #[cfg(feature = "json")]
+    fn json_body<T: serde::Serialize + ?Sized>(self, value: &T) -> Result<Response<Body>, Error> {
+        let body = Body {
+            content_type: mime::APPLICATION_JSON.to_string(),
+            inner: bytes::Bytes::from(serde_json::to_vec(value)?),
+        };
+        finish(self, body)
+    }
+}
+
+fn finish(builder: response::Builder, body: Body) -> Result<Response<Body>, Error> {
+    let has_content_type = builder
+        .headers_ref()
+        .is_some_and(|headers| headers.contains_key(header::CONTENT_TYPE));
+    let builder = if has_content_type {
+        builder
+    } else {
+        builder.header(header::CONTENT_TYPE, body.content_type.as_str())
+    };
+    Ok(builder.body(body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use ::http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn text() {
+        let res = Response::builder()
+            .status(StatusCode::CREATED)
+            .text_body("done")
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(
+            res.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(res.body().as_ref(), b"done");
+    }
+
+    #[test]
+    fn content_type_kept() {
+        let res = Response::builder()
+            .header(header::CONTENT_TYPE, "text/csv")
+            .text_body("a,b")
+            .unwrap();
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/csv");
+    }
+
+    #[test]
+    fn builder_error() {
+        let res = Response::builder().status(1000).text_body("x");
+        assert!(matches!(res, Err(Error::HttpError(_))));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json() {
+        let res = Response::builder()
+            .json_body(&serde_json::json!({"ok": true}))
+            .unwrap();
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(res.body().as_ref(), br#"{"ok":true}"#);
+    }
+}
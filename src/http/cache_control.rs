@@ -0,0 +1,229 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::fmt;
+use std::time::Duration;
+
+use ::http::HeaderValue;
+
+/// Parsed `Cache-Control` directives
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    /// `max-age=<seconds>`
+    pub max_age: Option<Duration>,
+    /// `s-maxage=<seconds>`
+    pub s_maxage: Option<Duration>,
+    /// `public`
+    pub public: bool,
+    /// `private`
+    pub private: bool,
+    /// `no-cache`
+    pub no_cache: bool,
+    /// `no-store`
+    pub no_store: bool,
+    /// `must-revalidate`
+    pub must_revalidate: bool,
+    /// `immutable`
+    pub immutable: bool,
+    /// `stale-while-revalidate=<seconds>`
+    pub stale_while_revalidate: Option<Duration>,
+}
+
+impl CacheControl {
+    /// Create a new `Cache-Control` [`Builder`]
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut directives = vec![];
+        if self.public {
+            directives.push("public".to_string());
+        }
+        if self.private {
+            directives.push("private".to_string());
+        }
+        if self.no_cache {
+            directives.push("no-cache".to_string());
+        }
+        if self.no_store {
+            directives.push("no-store".to_string());
+        }
+        if self.must_revalidate {
+            directives.push("must-revalidate".to_string());
+        }
+        if self.immutable {
+            directives.push("immutable".to_string());
+        }
+        if let Some(age) = self.max_age {
+            directives.push(format!("max-age={}", age.as_secs()));
+        }
+        if let Some(age) = self.s_maxage {
+            directives.push(format!("s-maxage={}", age.as_secs()));
+        }
+        if let Some(age) = self.stale_while_revalidate {
+            directives.push(format!("stale-while-revalidate={}", age.as_secs()));
+        }
+        f.write_str(&directives.join(", "))
+    }
+}
+
+/// Builder producing a `Cache-Control` header value
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    inner: CacheControl,
+}
+
+impl Builder {
+    /// Set `max-age`
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.inner.max_age = Some(age);
+        self
+    }
+
+    /// Set `s-maxage`, the lifetime in shared caches
+    pub fn s_maxage(mut self, age: Duration) -> Self {
+        self.inner.s_maxage = Some(age);
+        self
+    }
+
+    /// Mark response as cacheable by shared caches
+    pub fn public(mut self) -> Self {
+        self.inner.public = true;
+        self.inner.private = false;
+        self
+    }
+
+    /// Mark response as cacheable only by the client
+    pub fn private(mut self) -> Self {
+        self.inner.private = true;
+        self.inner.public = false;
+        self
+    }
+
+    /// Require revalidation before every reuse
+    pub fn no_cache(mut self) -> Self {
+        self.inner.no_cache = true;
+        self
+    }
+
+    /// Forbid storing the response in any cache
+    pub fn no_store(mut self) -> Self {
+        self.inner.no_store = true;
+        self
+    }
+
+    /// Forbid serving the response stale
+    pub fn must_revalidate(mut self) -> Self {
+        self.inner.must_revalidate = true;
+        self
+    }
+
+    /// Mark response as never changing while fresh
+    pub fn immutable(mut self) -> Self {
+        self.inner.immutable = true;
+        self
+    }
+
+    /// Set `stale-while-revalidate`
+    pub fn stale_while_revalidate(mut self, age: Duration) -> Self {
+        self.inner.stale_while_revalidate = Some(age);
+        self
+    }
+
+    /// Parsed representation of the directives
+    pub fn into_inner(self) -> CacheControl {
+        self.inner
+    }
+
+    /// Build the `Cache-Control` header value
+    pub fn build(self) -> HeaderValue {
+        HeaderValue::from_str(&self.inner.to_string())
+            .expect("cache-control directives are visible ASCII")
+    }
+}
+
+/// Parse a `Cache-Control` header value, unknown or malformed directives are ignored
+pub fn parse(value: &HeaderValue) -> CacheControl {
+    let mut cache_control = CacheControl::default();
+    let Ok(value) = value.to_str() else {
+        return cache_control;
+    };
+
+    for directive in value.split(',') {
+        let (name, arg) = match directive.split_once('=') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim().trim_matches('"'))),
+            None => (directive.trim(), None),
+        };
+        let seconds = arg
+            .and_then(|arg| arg.parse::<u64>().ok())
+            .map(Duration::from_secs);
+
+        match name.to_ascii_lowercase().as_str() {
+            "max-age" => cache_control.max_age = seconds,
+            "s-maxage" => cache_control.s_maxage = seconds,
+            "public" => cache_control.public = true,
+            "private" => cache_control.private = true,
+            "no-cache" => cache_control.no_cache = true,
+            "no-store" => cache_control.no_store = true,
+            "must-revalidate" => cache_control.must_revalidate = true,
+            "immutable" => cache_control.immutable = true,
+            "stale-while-revalidate" => cache_control.stale_while_revalidate = seconds,
+            _ => {}
+        }
+    }
+    cache_control
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build() {
+        let value = CacheControl::builder()
+            .private()
+            .public()
+            .max_age(Duration::from_secs(60))
+            .s_maxage(Duration::from_secs(300))
+            .stale_while_revalidate(Duration::from_secs(30))
+            .immutable()
+            .build();
+        assert_eq!(
+            value,
+            "public, immutable, max-age=60, s-maxage=300, stale-while-revalidate=30"
+        );
+        assert_eq!(CacheControl::builder().build(), "");
+    }
+
+    #[test]
+    fn parse_directives() {
+        let value = HeaderValue::from_static(
+            "Private, no-cache, NO-STORE, must-revalidate, max-age=\"10\", s-maxage=x, foo=1",
+        );
+        assert_eq!(
+            parse(&value),
+            CacheControl {
+                max_age: Some(Duration::from_secs(10)),
+                private: true,
+                no_cache: true,
+                no_store: true,
+                must_revalidate: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        let cache_control = CacheControl::builder()
+            .private()
+            .no_cache()
+            .max_age(Duration::ZERO)
+            .into_inner();
+        let value = HeaderValue::from_str(&cache_control.to_string()).unwrap();
+        assert_eq!(parse(&value), cache_control);
+    }
+}
@@ -0,0 +1,418 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+#[cfg(feature = "compression")]
+use std::io::{Read, Write};
+
+use ::http::HeaderValue;
+#[cfg(feature = "compression")]
+use ::http::{header, Response, StatusCode};
+
+#[cfg(feature = "compression")]
+use crate::body::Body;
+#[cfg(feature = "compression")]
+use crate::Error;
+
+/// Bodies smaller than this are not worth compressing
+#[cfg(feature = "compression")]
+pub const MIN_COMPRESS_SIZE: usize = 1024;
+
+/// Content coding supported by the SDK
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// `gzip`
+    Gzip,
+    /// `deflate` (zlib format)
+    Deflate,
+    /// `br`, enabled with the `brotli` feature
+    #[cfg(feature = "brotli")]
+    Brotli,
+}
+
+impl Encoding {
+    /// Supported encodings in order of server preference
+    #[cfg(not(feature = "brotli"))]
+    pub const ALL: [Encoding; 2] = [Encoding::Gzip, Encoding::Deflate];
+    /// Supported encodings in order of server preference
+    #[cfg(feature = "brotli")]
+    pub const ALL: [Encoding; 3] = [Encoding::Brotli, Encoding::Gzip, Encoding::Deflate];
+
+    /// Content coding token
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli => "br",
+        }
+    }
+}
+
+/// Choose the content coding for a response from the request `Accept-Encoding` header.
+///
+/// The encoding with the highest q-value is chosen, ties are broken by server preference and
+/// `None` means the response must be sent uncompressed.
+pub fn negotiate_encoding(accept_encoding: &HeaderValue) -> Option<Encoding> {
+    let Ok(accept_encoding) = accept_encoding.to_str() else {
+        return None;
+    };
+
+    let mut wildcard = None;
+    let mut weights = [None; Encoding::ALL.len()];
+    for item in accept_encoding.split(',') {
+        let mut params = item.split(';');
+        let coding = params.next().unwrap_or_default().trim();
+        let q = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if coding == "*" {
+            wildcard = Some(q);
+        } else if let Some(index) = Encoding::ALL
+            .iter()
+            .position(|encoding| coding.eq_ignore_ascii_case(encoding.as_str()))
+        {
+            weights[index] = Some(q);
+        }
+    }
+
+    let mut best = None;
+    let mut best_q = 0.0;
+    for (encoding, weight) in Encoding::ALL.iter().zip(weights) {
+        let q = weight.or(wildcard).unwrap_or_default();
+        if q > best_q {
+            best = Some(*encoding);
+            best_q = q;
+        }
+    }
+    best
+}
+
+/// Compress the response body with the encoding negotiated from `accept_encoding`.
+///
+/// The response is returned unchanged when the body is smaller than [`MIN_COMPRESS_SIZE`],
+/// already encoded or not a textual content type. Otherwise `Accept-Encoding` is added to `Vary`,
+/// also when the client doesn't accept a supported encoding or compression would not make the
+/// body smaller, so shared caches keep the compressed and uncompressed variants apart. A
+/// compressed body gets `Content-Encoding` set.
+#[cfg(feature = "compression")]
+pub fn compress(mut res: Response<Body>, accept_encoding: Option<&HeaderValue>) -> Response<Body> {
+    if !is_compressible(&res) {
+        return res;
+    }
+    super::vary::add(&mut res, "accept-encoding");
+    let Some(encoding) = accept_encoding.and_then(negotiate_encoding) else {
+        return res;
+    };
+
+    let (mut parts, body) = res.into_parts();
+    let compressed = match encode(encoding, &body) {
+        Ok(compressed) if compressed.len() < body.len() => compressed,
+        _ => return Response::from_parts(parts, body),
+    };
+
+    parts.headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.as_str()),
+    );
+    if parts.headers.contains_key(header::CONTENT_LENGTH) {
+        parts
+            .headers
+            .insert(header::CONTENT_LENGTH, HeaderValue::from(compressed.len()));
+    }
+
+    let body = Body {
+        content_type: body.content_type,
+        inner: compressed.into(),
+    };
+    Response::from_parts(parts, body)
+}
+
+/// Decode a `gzip`, `deflate` or, with the `brotli` feature, `br` encoded response body.
+///
+/// `Content-Encoding` and a stale `Content-Length` are removed. Responses without a
+/// `Content-Encoding`, with an unsupported or stacked one, or with an empty body are returned
+/// unchanged; a body that fails to decode is an [`Error::InvalidBody`].
+///
+/// The decoded size is not limited, use [`decompress_limited`] for untrusted backends.
+#[cfg(feature = "compression")]
+pub fn decompress(res: Response<Body>) -> Result<Response<Body>, Error> {
+    decode_response(res, None)
+}
+
+/// [`decompress`] failing with [`Error::ResponseTooLarge`] as soon as the decoded body exceeds
+/// `max` bytes, so a small compressed body can't expand without bound
+#[cfg(feature = "compression")]
+pub fn decompress_limited(res: Response<Body>, max: usize) -> Result<Response<Body>, Error> {
+    decode_response(res, Some(max))
+}
+
+#[cfg(feature = "compression")]
+fn decode_response(res: Response<Body>, limit: Option<usize>) -> Result<Response<Body>, Error> {
+    let encoding = match res
+        .headers()
+        .get(header::CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("gzip" | "x-gzip") => Encoding::Gzip,
+        Some("deflate") => Encoding::Deflate,
+        #[cfg(feature = "brotli")]
+        Some("br") => Encoding::Brotli,
+        _ => return Ok(res),
+    };
+    if res.body().is_empty() {
+        return Ok(res);
+    }
+
+    let (mut parts, body) = res.into_parts();
+    let decoded = decode(encoding, &body, limit).map_err(|_| Error::InvalidBody)?;
+    if let Some(limit) = limit.filter(|limit| decoded.len() > *limit) {
+        return Err(Error::ResponseTooLarge(limit));
+    }
+    parts.headers.remove(header::CONTENT_ENCODING);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let body = Body {
+        content_type: body.content_type,
+        inner: decoded.into(),
+    };
+    Ok(Response::from_parts(parts, body))
+}
+
+#[cfg(feature = "compression")]
+fn is_compressible(res: &Response<Body>) -> bool {
+    if res.body().len() < MIN_COMPRESS_SIZE
+        || matches!(
+            res.status(),
+            StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED | StatusCode::PARTIAL_CONTENT
+        )
+        || res.headers().contains_key(header::CONTENT_ENCODING)
+    {
+        return false;
+    }
+
+    let mime = match res.headers().get(header::CONTENT_TYPE) {
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(|value| value.parse::<mime::Mime>().ok()),
+        None => res.body().mime(),
+    };
+    let Some(mime) = mime else {
+        return false;
+    };
+    mime.type_() == mime::TEXT
+        || mime.suffix() == Some(mime::JSON)
+        || mime.suffix() == Some(mime::XML)
+        || (mime.type_() == mime::APPLICATION
+            && matches!(mime.subtype().as_str(), "json" | "javascript" | "xml"))
+}
+
+#[cfg(feature = "compression")]
+fn encode(encoding: Encoding, data: &[u8]) -> std::io::Result<Vec<u8>> {
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+
+    match encoding {
+        Encoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        Encoding::Deflate => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "brotli")]
+        Encoding::Brotli => {
+            // quality 5 keeps per-request compression time low, 11 is meant for static assets
+            let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+            encoder.write_all(data)?;
+            encoder.flush()?;
+            Ok(encoder.into_inner())
+        }
+    }
+}
+
+#[cfg(feature = "compression")]
+fn decode(encoding: Encoding, data: &[u8], limit: Option<usize>) -> std::io::Result<Vec<u8>> {
+    use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+
+    let mut decoded = Vec::new();
+    match encoding {
+        Encoding::Gzip => {
+            read_limited(GzDecoder::new(data), limit, &mut decoded)?;
+        }
+        Encoding::Deflate => {
+            // some servers send raw deflate instead of the zlib format
+            if read_limited(ZlibDecoder::new(data), limit, &mut decoded).is_err() {
+                decoded.clear();
+                read_limited(DeflateDecoder::new(data), limit, &mut decoded)?;
+            }
+        }
+        #[cfg(feature = "brotli")]
+        Encoding::Brotli => {
+            read_limited(brotli::Decompressor::new(data, 4096), limit, &mut decoded)?;
+        }
+    }
+    Ok(decoded)
+}
+
+/// read at most one byte past `limit`, enough for the caller to tell the output is too large
+#[cfg(feature = "compression")]
+fn read_limited(
+    mut reader: impl Read,
+    limit: Option<usize>,
+    buf: &mut Vec<u8>,
+) -> std::io::Result<()> {
+    match limit {
+        Some(limit) => reader.take(limit as u64 + 1).read_to_end(buf)?,
+        None => reader.read_to_end(buf)?,
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negotiate(accept_encoding: &'static str) -> Option<Encoding> {
+        negotiate_encoding(&HeaderValue::from_static(accept_encoding))
+    }
+
+    #[test]
+    fn negotiation() {
+        assert_eq!(negotiate("gzip"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("deflate, gzip;q=0.5"), Some(Encoding::Deflate));
+        assert_eq!(negotiate("GZIP;q=0.2, deflate;q=0.1"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("identity"), None);
+        assert_eq!(negotiate("gzip;q=0, deflate;q=0"), None);
+        assert_eq!(negotiate(""), None);
+        // ties go to server preference
+        assert_eq!(negotiate("deflate, gzip"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("*"), Some(Encoding::ALL[0]));
+        let not_gzip = Encoding::ALL.into_iter().find(|e| *e != Encoding::Gzip);
+        assert_eq!(negotiate("*;q=0.5, gzip;q=0"), not_gzip);
+    }
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn negotiation_brotli() {
+        assert_eq!(negotiate("gzip, deflate, br"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("br;q=0.5, gzip"), Some(Encoding::Gzip));
+    }
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod compression_tests {
+    use super::*;
+
+    fn text(len: usize) -> Response<Body> {
+        Response::builder()
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body(Body::from("a".repeat(len)))
+            .unwrap()
+    }
+
+    fn vary(res: &Response<Body>) -> Option<&str> {
+        res.headers()
+            .get(header::VARY)
+            .and_then(|value| value.to_str().ok())
+    }
+
+    #[test]
+    fn compress_round_trip() {
+        let accept = HeaderValue::from_static("gzip");
+        let res = compress(text(4096), Some(&accept));
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(vary(&res), Some("accept-encoding"));
+        assert!(res.body().len() < 4096);
+
+        let res = decompress(res).unwrap();
+        assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(&res.body()[..], "a".repeat(4096).as_bytes());
+    }
+
+    #[test]
+    fn vary_without_compression() {
+        let res = compress(text(4096), None);
+        assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(vary(&res), Some("accept-encoding"));
+
+        let identity = HeaderValue::from_static("identity");
+        let res = compress(text(4096), Some(&identity));
+        assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(vary(&res), Some("accept-encoding"));
+    }
+
+    #[test]
+    fn ineligible_unchanged() {
+        let accept = HeaderValue::from_static("gzip");
+
+        let res = compress(text(MIN_COMPRESS_SIZE - 1), Some(&accept));
+        assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(vary(&res), None);
+
+        let image = Response::builder()
+            .header(header::CONTENT_TYPE, "image/png")
+            .body(Body::from(vec![0; 4096]))
+            .unwrap();
+        let res = compress(image, Some(&accept));
+        assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(vary(&res), None);
+
+        let mut partial = text(4096);
+        *partial.status_mut() = StatusCode::PARTIAL_CONTENT;
+        let res = compress(partial, Some(&accept));
+        assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+    }
+
+    #[test]
+    fn decompress_passthrough() {
+        let res = decompress(text(10)).unwrap();
+        assert_eq!(res.body().len(), 10);
+
+        let stacked = Response::builder()
+            .header(header::CONTENT_ENCODING, "gzip, br")
+            .body(Body::from("x"))
+            .unwrap();
+        assert_eq!(&decompress(stacked).unwrap().body()[..], b"x");
+
+        let corrupt = Response::builder()
+            .header(header::CONTENT_ENCODING, "gzip")
+            .body(Body::from("not gzip"))
+            .unwrap();
+        assert!(matches!(decompress(corrupt), Err(Error::InvalidBody)));
+    }
+
+    fn encoded(encoding: Encoding, data: &[u8]) -> Response<Body> {
+        Response::builder()
+            .header(header::CONTENT_ENCODING, encoding.as_str())
+            .body(Body::from(encode(encoding, data).unwrap()))
+            .unwrap()
+    }
+
+    #[test]
+    fn decompress_limited_rejects_bomb() {
+        let data = vec![0; 1 << 20];
+        for encoding in Encoding::ALL {
+            let res = encoded(encoding, &data);
+            assert!(res.body().len() < 4096);
+            assert!(matches!(
+                decompress_limited(res, 64 * 1024),
+                Err(Error::ResponseTooLarge(65536))
+            ));
+        }
+    }
+
+    #[test]
+    fn decompress_limited_at_limit() {
+        let data = vec![b'a'; 4096];
+        for encoding in Encoding::ALL {
+            let res = decompress_limited(encoded(encoding, &data), data.len()).unwrap();
+            assert_eq!(&res.body()[..], &data[..]);
+            assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+        }
+    }
+}
@@ -0,0 +1,295 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::time::Duration;
+
+use ::http::{header, HeaderMap, HeaderValue, Method, Request, Response, StatusCode};
+
+use super::vary;
+use crate::body::Body;
+
+/// CORS policy applied to requests at runtime.
+///
+/// Nothing is allowed until origins are added; methods default to `GET`, `HEAD` and `POST`.
+#[derive(Debug, Clone)]
+pub struct Cors {
+    any_origin: bool,
+    origins: Vec<String>,
+    methods: Vec<Method>,
+    headers: Vec<String>,
+    max_age: Option<Duration>,
+    credentials: bool,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self {
+            any_origin: false,
+            origins: vec![],
+            methods: vec![Method::GET, Method::HEAD, Method::POST],
+            headers: vec![],
+            max_age: None,
+            credentials: false,
+        }
+    }
+}
+
+impl Cors {
+    /// Policy allowing no origins
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow an origin such as `https://app.example.com`, `*` allows any origin
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        if origin == "*" {
+            self.any_origin = true;
+        } else {
+            self.origins.push(origin.trim_end_matches('/').to_string());
+        }
+        self
+    }
+
+    /// Allow any origin
+    pub fn allow_any_origin(self) -> Self {
+        self.allow_origin("*")
+    }
+
+    /// Methods allowed for cross-origin requests, replacing the defaults
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Request headers allowed for cross-origin requests
+    pub fn allow_headers<S: Into<String>>(mut self, headers: impl IntoIterator<Item = S>) -> Self {
+        self.headers = headers
+            .into_iter()
+            .map(|name| name.into().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// How long browsers may cache a preflight result
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Allow cookies and credentials, the origin is then echoed instead of `*`
+    pub fn allow_credentials(mut self) -> Self {
+        self.credentials = true;
+        self
+    }
+
+    /// Answer a preflight request, `None` if the request is not a preflight.
+    ///
+    /// A preflight for a disallowed origin, method or header gets `204 No Content` without CORS
+    /// headers, which makes the browser block the actual request.
+    pub fn preflight(&self, req: &Request<Body>) -> Option<Response<Body>> {
+        let headers = req.headers();
+        if req.method() != Method::OPTIONS || !headers.contains_key(header::ORIGIN) {
+            return None;
+        }
+        let requested_method = headers
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)?
+            .to_str()
+            .ok()?;
+
+        let mut res = Response::new(Body::empty());
+        *res.status_mut() = StatusCode::NO_CONTENT;
+        let res_headers = res.headers_mut();
+        vary::add_to(res_headers, "origin");
+        vary::add_to(res_headers, "access-control-request-method");
+        vary::add_to(res_headers, "access-control-request-headers");
+
+        let method_allowed = self
+            .methods
+            .iter()
+            .any(|method| method.as_str() == requested_method);
+        let headers_allowed = headers
+            .get_all(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .all(|name| {
+                self.headers
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(name))
+            });
+        if !method_allowed || !headers_allowed || !self.set_origin(headers, res_headers) {
+            return Some(res);
+        }
+
+        let methods = self
+            .methods
+            .iter()
+            .map(Method::as_str)
+            .collect::<Vec<&str>>()
+            .join(", ");
+        if let Ok(methods) = HeaderValue::from_str(&methods) {
+            res_headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+        if !self.headers.is_empty() {
+            if let Ok(allowed) = HeaderValue::from_str(&self.headers.join(", ")) {
+                res_headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allowed);
+            }
+        }
+        if let Some(max_age) = self.max_age {
+            res_headers.insert(
+                header::ACCESS_CONTROL_MAX_AGE,
+                HeaderValue::from(max_age.as_secs()),
+            );
+        }
+        Some(res)
+    }
+
+    /// Add CORS headers to the response of an actual cross-origin request
+    pub fn apply(&self, req: &Request<Body>, mut res: Response<Body>) -> Response<Body> {
+        if req.headers().contains_key(header::ORIGIN) {
+            vary::add_to(res.headers_mut(), "origin");
+            self.set_origin(req.headers(), res.headers_mut());
+        }
+        res
+    }
+
+    /// set `Access-Control-Allow-Origin` and credentials for an allowed origin
+    fn set_origin(&self, req_headers: &HeaderMap, res_headers: &mut HeaderMap) -> bool {
+        let Some(origin) = req_headers.get(header::ORIGIN) else {
+            return false;
+        };
+        let allowed = self.any_origin
+            || origin.to_str().is_ok_and(|origin| {
+                self.origins
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+            });
+        if !allowed {
+            return false;
+        }
+
+        let allow_origin = if self.any_origin && !self.credentials {
+            HeaderValue::from_static("*")
+        } else {
+            origin.clone()
+        };
+        res_headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if self.credentials {
+            res_headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preflight(headers: &[(header::HeaderName, &str)]) -> Request<Body> {
+        let mut req = Request::builder().method(Method::OPTIONS);
+        for (name, value) in headers {
+            req = req.header(name, *value);
+        }
+        req.body(Body::empty()).unwrap()
+    }
+
+    fn policy() -> Cors {
+        Cors::new()
+            .allow_origin("https://app.example.com/")
+            .allow_methods([Method::GET, Method::PUT])
+            .allow_headers(["Content-Type"])
+            .max_age(Duration::from_secs(600))
+    }
+
+    #[test]
+    fn allowed_preflight() {
+        let req = preflight(&[
+            (header::ORIGIN, "https://app.example.com"),
+            (header::ACCESS_CONTROL_REQUEST_METHOD, "PUT"),
+            (header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type"),
+        ]);
+        let res = policy().preflight(&req).unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let headers = res.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET, PUT");
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "content-type"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert!(headers[header::VARY].to_str().unwrap().contains("origin"));
+    }
+
+    #[test]
+    fn rejected_preflight() {
+        for (origin, method, headers) in [
+            ("https://evil.example.com", "PUT", ""),
+            ("https://app.example.com", "DELETE", ""),
+            ("https://app.example.com", "PUT", "content-type, x-secret"),
+        ] {
+            let req = preflight(&[
+                (header::ORIGIN, origin),
+                (header::ACCESS_CONTROL_REQUEST_METHOD, method),
+                (header::ACCESS_CONTROL_REQUEST_HEADERS, headers),
+            ]);
+            let res = policy().preflight(&req).unwrap();
+            assert_eq!(res.status(), StatusCode::NO_CONTENT);
+            assert!(!res
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        }
+    }
+
+    #[test]
+    fn not_a_preflight() {
+        assert!(policy().preflight(&preflight(&[])).is_none());
+        let req = preflight(&[(header::ORIGIN, "https://app.example.com")]);
+        assert!(policy().preflight(&req).is_none());
+        let req = Request::builder()
+            .header(header::ORIGIN, "https://app.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .body(Body::empty())
+            .unwrap();
+        assert!(policy().preflight(&req).is_none());
+    }
+
+    #[test]
+    fn apply_origin() {
+        let req = Request::builder()
+            .header(header::ORIGIN, "https://app.example.com")
+            .body(Body::empty())
+            .unwrap();
+        let res = Cors::new()
+            .allow_any_origin()
+            .apply(&req, Response::new(Body::empty()));
+        assert_eq!(res.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+
+        let res = Cors::new()
+            .allow_any_origin()
+            .allow_credentials()
+            .apply(&req, Response::new(Body::empty()));
+        assert_eq!(
+            res.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(
+            res.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS],
+            "true"
+        );
+
+        let res = Cors::new().apply(&req, Response::new(Body::empty()));
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(res.headers()[header::VARY], "origin");
+    }
+}
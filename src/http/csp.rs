@@ -0,0 +1,133 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::fmt;
+
+use ::http::HeaderValue;
+
+/// `'self'` source
+pub const SELF: &str = "'self'";
+/// `'none'` source
+pub const NONE: &str = "'none'";
+/// `'unsafe-inline'` source
+pub const UNSAFE_INLINE: &str = "'unsafe-inline'";
+/// `'strict-dynamic'` source
+pub const STRICT_DYNAMIC: &str = "'strict-dynamic'";
+
+/// `Content-Security-Policy` builder
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    directives: Vec<(String, Vec<String>)>,
+}
+
+impl Policy {
+    /// Create an empty policy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add sources to a directive, creating it if needed
+    pub fn directive(mut self, name: &str, sources: &[&str]) -> Self {
+        let sources = sources.iter().map(|source| source.to_string());
+        match self.directives.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => existing.extend(sources),
+            None => self.directives.push((name.to_string(), sources.collect())),
+        }
+        self
+    }
+
+    /// `default-src` directive
+    pub fn default_src(self, sources: &[&str]) -> Self {
+        self.directive("default-src", sources)
+    }
+
+    /// `script-src` directive
+    pub fn script_src(self, sources: &[&str]) -> Self {
+        self.directive("script-src", sources)
+    }
+
+    /// `style-src` directive
+    pub fn style_src(self, sources: &[&str]) -> Self {
+        self.directive("style-src", sources)
+    }
+
+    /// `img-src` directive
+    pub fn img_src(self, sources: &[&str]) -> Self {
+        self.directive("img-src", sources)
+    }
+
+    /// `connect-src` directive
+    pub fn connect_src(self, sources: &[&str]) -> Self {
+        self.directive("connect-src", sources)
+    }
+
+    /// `object-src` directive
+    pub fn object_src(self, sources: &[&str]) -> Self {
+        self.directive("object-src", sources)
+    }
+
+    /// `frame-ancestors` directive
+    pub fn frame_ancestors(self, sources: &[&str]) -> Self {
+        self.directive("frame-ancestors", sources)
+    }
+
+    /// Allow scripts and styles carrying the `nonce` attribute
+    pub fn nonce(self, nonce: &str) -> Self {
+        let source = format!("'nonce-{}'", nonce);
+        self.script_src(&[&source]).style_src(&[&source])
+    }
+
+    /// Build the header value, `None` if a source contains characters not allowed in headers
+    pub fn to_header_value(&self) -> Option<HeaderValue> {
+        HeaderValue::from_str(&self.to_string()).ok()
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (name, sources)) in self.directives.iter().enumerate() {
+            if index > 0 {
+                f.write_str("; ")?;
+            }
+            f.write_str(name)?;
+            for source in sources {
+                write!(f, " {}", source)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Generate a fresh per-response nonce from the host CSPRNG
+pub fn generate_nonce() -> String {
+    crate::encoding::hex::encode(&crate::random::bytes(16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy() {
+        let policy = Policy::new()
+            .default_src(&[SELF])
+            .object_src(&[NONE])
+            .nonce("abc")
+            .script_src(&[STRICT_DYNAMIC]);
+        assert_eq!(
+            policy.to_string(),
+            "default-src 'self'; object-src 'none'; script-src 'nonce-abc' 'strict-dynamic'; \
+             style-src 'nonce-abc'"
+        );
+        assert!(policy.to_header_value().is_some());
+        assert!(Policy::new().img_src(&["a\nb"]).to_header_value().is_none());
+    }
+
+    #[test]
+    fn nonce() {
+        let nonce = generate_nonce();
+        assert_eq!(nonce.len(), 32);
+        assert!(nonce.bytes().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(nonce, generate_nonce());
+    }
+}
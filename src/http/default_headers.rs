@@ -0,0 +1,55 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{HeaderName, HeaderValue, Response};
+
+use crate::body::Body;
+
+/// Insert `headers` into the response unless the handler has already set them.
+///
+/// Used by `#[fastedge::http(default_headers(...))]` to add headers such as `Server` or
+/// `X-Content-Type-Options` to every response. Invalid header names or values are skipped.
+pub fn with_default_headers(mut res: Response<Body>, headers: &[(&str, &str)]) -> Response<Body> {
+    for (name, value) in headers {
+        let (Ok(name), Ok(value)) = (HeaderName::try_from(*name), HeaderValue::try_from(*value))
+        else {
+            continue;
+        };
+        res.headers_mut().entry(name).or_insert(value);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use ::http::header;
+
+    use super::*;
+
+    #[test]
+    fn adds_missing_headers() {
+        let res = Response::builder()
+            .header(header::SERVER, "handler")
+            .body(Body::empty())
+            .unwrap();
+        let res = with_default_headers(
+            res,
+            &[
+                ("server", "fastedge"),
+                ("x-content-type-options", "nosniff"),
+            ],
+        );
+        assert_eq!(res.headers()[header::SERVER], "handler");
+        assert_eq!(res.headers()[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+    }
+
+    #[test]
+    fn skips_invalid_headers() {
+        let res = with_default_headers(
+            Response::new(Body::empty()),
+            &[("bad name", "x"), ("x-bad-value", "a\nb"), ("x-ok", "1")],
+        );
+        assert_eq!(res.headers().len(), 1);
+        assert_eq!(res.headers()["x-ok"], "1");
+    }
+}
@@ -0,0 +1,102 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, HeaderValue, Response, StatusCode};
+
+use std::fmt::Write;
+
+use bytes::Bytes;
+
+use crate::body::Body;
+
+/// Response with the given status and an empty body, built without any fallible step
+pub fn status_response(status: StatusCode) -> Response<Body> {
+    let mut res = Response::new(Body::empty());
+    *res.status_mut() = status;
+    res
+}
+
+/// Plain text response with the given status, built without any fallible step
+pub fn error_response(status: StatusCode, msg: impl Into<String>) -> Response<Body> {
+    let mut res = Response::new(Body::from(msg.into()));
+    *res.status_mut() = status;
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    res
+}
+
+/// `500 Internal Server Error` plain text response
+pub fn internal_error(msg: impl Into<String>) -> Response<Body> {
+    error_response(StatusCode::INTERNAL_SERVER_ERROR, msg)
+}
+
+/// `{"error": "<msg>"}` JSON response with the given status, available without the `json` feature
+pub fn json_error_response(status: StatusCode, msg: &str) -> Response<Body> {
+    let mut json = String::with_capacity(msg.len() + 12);
+    json.push_str("{\"error\":\"");
+    for c in msg.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push_str("\"}");
+
+    let body = Body {
+        content_type: mime::APPLICATION_JSON.to_string(),
+        inner: Bytes::from(json),
+    };
+    let mut res = Response::new(body);
+    *res.status_mut() = status;
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_error() {
+        let res = error_response(StatusCode::NOT_FOUND, "missing\n");
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            res.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(res.body().as_ref(), b"missing\n");
+        let res = internal_error("boom");
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn json_error_escaping() {
+        let res = json_error_response(StatusCode::BAD_REQUEST, "bad \"x\"\\\n\t\u{1}é");
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(
+            std::str::from_utf8(res.body()).unwrap(),
+            r#"{"error":"bad \"x\"\\\n\t\u0001é"}"#
+        );
+    }
+
+    #[test]
+    fn bodyless_status() {
+        let res = status_response(StatusCode::NO_CONTENT);
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert!(res.body().is_empty());
+        assert!(res.headers().is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, HeaderValue, Request};
+
+use crate::body::Body;
+
+/// Compute a strong `ETag` for the given content
+pub fn compute(bytes: &[u8]) -> HeaderValue {
+    // FNV-1a is stable across builds, which keeps ETags valid between app deployments
+    let hash = bytes.iter().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    });
+    HeaderValue::from_str(&format!("\"{:x}-{:016x}\"", bytes.len(), hash))
+        .expect("etag is visible ASCII")
+}
+
+/// Check an `If-None-Match` header value against an `ETag` using the weak comparison
+pub fn matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(if_none_match) = if_none_match.to_str() else {
+        return false;
+    };
+    let Ok(etag) = etag.to_str() else {
+        return false;
+    };
+    if if_none_match.trim() == "*" {
+        return true;
+    }
+    let etag = opaque_tag(etag);
+    if_none_match
+        .split(',')
+        .any(|candidate| opaque_tag(candidate) == etag)
+}
+
+/// `If-Match` header value of a conditional write request, if any
+pub fn if_match(req: &Request<Body>) -> Option<&str> {
+    req.headers()
+        .get(header::IF_MATCH)?
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Check an `If-Match` header value against the current `ETag` using the strong comparison.
+///
+/// When this returns `false` the write must be rejected with `412 Precondition Failed`.
+pub fn if_match_satisfied(if_match: &str, etag: &HeaderValue) -> bool {
+    let Ok(etag) = etag.to_str() else {
+        return false;
+    };
+    let etag = etag.trim();
+    if etag.starts_with("W/") {
+        return false;
+    }
+    if if_match.trim() == "*" {
+        return true;
+    }
+    if_match
+        .split(',')
+        .any(|candidate| candidate.trim() == etag)
+}
+
+fn opaque_tag(etag: &str) -> &str {
+    let etag = etag.trim();
+    etag.strip_prefix("W/").unwrap_or(etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_is_stable() {
+        assert_eq!(compute(b""), "\"0-cbf29ce484222325\"");
+        assert_eq!(compute(b"hello"), compute(b"hello"));
+        assert_ne!(compute(b"hello"), compute(b"world"));
+    }
+
+    #[test]
+    fn if_none_match() {
+        let etag = HeaderValue::from_static("\"abc\"");
+        let matching = |value| matches(&HeaderValue::from_static(value), &etag);
+        assert!(matching("\"abc\""));
+        assert!(matching("W/\"abc\""));
+        assert!(matching("\"x\", W/\"abc\""));
+        assert!(matching("*"));
+        assert!(!matching("\"abcd\""));
+        assert!(!matching(""));
+    }
+
+    #[test]
+    fn if_match_header() {
+        let req = Request::builder()
+            .header(header::IF_MATCH, " \"abc\" ")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(if_match(&req), Some("\"abc\""));
+        assert_eq!(if_match(&Request::new(Body::empty())), None);
+    }
+
+    #[test]
+    fn if_match_strong_comparison() {
+        let etag = HeaderValue::from_static("\"abc\"");
+        assert!(if_match_satisfied("\"abc\"", &etag));
+        assert!(if_match_satisfied("\"x\", \"abc\"", &etag));
+        assert!(if_match_satisfied("*", &etag));
+        assert!(!if_match_satisfied("W/\"abc\"", &etag));
+        assert!(!if_match_satisfied("\"x\"", &etag));
+        assert!(!if_match_satisfied(
+            "*",
+            &HeaderValue::from_static("W/\"abc\"")
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::net::{IpAddr, SocketAddr};
+
+use ::http::{header, Request};
+
+use super::header_map_ext::split_quoted;
+use super::HeaderMapExt;
+use crate::body::Body;
+
+/// One proxy hop of an RFC 7239 `Forwarded` header, values are unquoted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForwardedElement {
+    /// `for`: the client or previous proxy, an address, `unknown` or an obfuscated `_name`
+    pub forwarded_for: Option<String>,
+    /// `by`: the interface where the request came in to the proxy
+    pub by: Option<String>,
+    /// `host`: the `Host` header received by the proxy
+    pub host: Option<String>,
+    /// `proto`: the protocol used to make the request, e.g. `https`
+    pub proto: Option<String>,
+}
+
+impl ForwardedElement {
+    /// IP address of the `for` parameter, `None` for `unknown` and obfuscated identifiers.
+    ///
+    /// IPv6 addresses are bracketed and both IPv4 and IPv6 may carry a port.
+    pub fn for_ip(&self) -> Option<IpAddr> {
+        let node = self.forwarded_for.as_deref()?;
+        node.parse::<SocketAddr>()
+            .map(|addr| addr.ip())
+            .or_else(|_| node.trim_start_matches('[').trim_end_matches(']').parse())
+            .ok()
+    }
+}
+
+/// Parse all `Forwarded` headers into the list of hops, closest to the client first.
+///
+/// Parameter names are case-insensitive and unknown parameters are ignored. Malformed pairs are
+/// skipped rather than failing the whole header.
+pub fn parse(req: &Request<Body>) -> Vec<ForwardedElement> {
+    req.headers()
+        .get_comma_split(header::FORWARDED)
+        .iter()
+        .map(|element| {
+            let mut parsed = ForwardedElement::default();
+            for pair in split_quoted(element, ';') {
+                let Some((name, value)) = pair.split_once('=') else {
+                    continue;
+                };
+                let value = Some(unquote(value.trim()));
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "for" => parsed.forwarded_for = value,
+                    "by" => parsed.by = value,
+                    "host" => parsed.host = value,
+                    "proto" => parsed.proto = value,
+                    _ => {}
+                }
+            }
+            parsed
+        })
+        .collect()
+}
+
+/// remove the quotes and backslash escapes of a quoted string, tokens are returned as is
+fn unquote(value: &str) -> String {
+    let Some(inner) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    else {
+        return value.to_string();
+    };
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forwarded(values: &[&str]) -> Vec<ForwardedElement> {
+        let mut req = Request::builder();
+        for value in values {
+            req = req.header(header::FORWARDED, *value);
+        }
+        parse(&req.body(Body::empty()).unwrap())
+    }
+
+    #[test]
+    fn hops() {
+        let hops = forwarded(&[
+            "for=192.0.2.60;Proto=https;by=203.0.113.43;host=example.com",
+            "for=\"[2001:db8:cafe::17]:4711\", for=_hidden",
+        ]);
+        assert_eq!(hops.len(), 3);
+        assert_eq!(
+            hops[0],
+            ForwardedElement {
+                forwarded_for: Some("192.0.2.60".to_string()),
+                by: Some("203.0.113.43".to_string()),
+                host: Some("example.com".to_string()),
+                proto: Some("https".to_string()),
+            }
+        );
+        assert_eq!(hops[0].for_ip(), "192.0.2.60".parse().ok());
+        assert_eq!(hops[1].for_ip(), "2001:db8:cafe::17".parse().ok());
+        assert_eq!(hops[2].forwarded_for.as_deref(), Some("_hidden"));
+        assert_eq!(hops[2].for_ip(), None);
+    }
+
+    #[test]
+    fn quoted_values_and_malformed_pairs() {
+        let hops = forwarded(&[r#"for="[::1]";host="a\"b;c";junk;secret=1"#]);
+        assert_eq!(hops.len(), 1);
+        assert_eq!(hops[0].for_ip(), "::1".parse().ok());
+        assert_eq!(hops[0].host.as_deref(), Some("a\"b;c"));
+        assert!(forwarded(&[]).is_empty());
+    }
+}
@@ -0,0 +1,214 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{Response, StatusCode};
+
+use super::error_response;
+use crate::body::Body;
+
+/// Handler error carrying the response status it should produce
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message}")]
+pub struct HandlerError {
+    /// Response status
+    pub status: StatusCode,
+    /// Message returned as plain text body
+    pub message: String,
+}
+
+impl HandlerError {
+    /// Error with the given status and message
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    /// Plain text response with the error status and message
+    pub fn into_response(self) -> Response<Body> {
+        error_response(self.status, self.message)
+    }
+}
+
+impl From<HandlerError> for Response<Body> {
+    fn from(error: HandlerError) -> Self {
+        error.into_response()
+    }
+}
+
+/// Lets handlers returning `Result<_, HandlerError>` use `?` on [`send_request`](crate::send_request),
+/// the status comes from [`status_hint`](crate::Error::status_hint)
+impl From<crate::Error> for HandlerError {
+    fn from(error: crate::Error) -> Self {
+        Self::new(error.status_hint(), error.to_string())
+    }
+}
+
+/// Plain text response with the [`status_hint`](crate::Error::status_hint) status, used by the
+/// `#[fastedge::http]` macro for handlers returning `Result<_, fastedge::Error>`
+impl From<crate::Error> for Response<Body> {
+    fn from(error: crate::Error) -> Self {
+        HandlerError::from(error).into_response()
+    }
+}
+
+/// Lets handlers returning `Result<_, HandlerError>` use `?` on response builders, failures are
+/// `500 Internal Server Error`
+impl From<::http::Error> for HandlerError {
+    fn from(error: ::http::Error) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+    }
+}
+
+/// Plain text error response for an [`anyhow::Error`], enabled with the `anyhow` feature.
+///
+/// The error chain is searched from the outermost error inwards: the first [`HandlerError`]
+/// provides status and message, otherwise the first [`fastedge::Error`](crate::Error) provides
+/// the status through [`status_hint`](crate::Error::status_hint) and the message is the outermost
+/// error. Anything else is a `500 Internal Server Error`.
+#[cfg(feature = "anyhow")]
+pub fn anyhow_response(error: &anyhow::Error) -> Response<Body> {
+    if let Some(error) = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<HandlerError>())
+    {
+        return error.clone().into_response();
+    }
+    let status = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<crate::Error>())
+        .map_or(StatusCode::INTERNAL_SERVER_ERROR, crate::Error::status_hint);
+    error_response(status, error.to_string())
+}
+
+/// Handler error dispatch for the `#[fastedge::http]` macro.
+///
+/// `(&error).fastedge_error_kind()` selects, by autoref, a tag for the concrete error type: errors
+/// convertible into `Response<Body>` (e.g. [`HandlerError`], [`fastedge::Error`](crate::Error))
+/// become that response, `anyhow::Error` goes through `anyhow_response` with the `anyhow`
+/// feature, and any other [`Display`](std::fmt::Display) error yields its message for the
+/// macro's `500 Internal Server Error`.
+#[doc(hidden)]
+pub mod kind {
+    use std::fmt::Display;
+
+    use ::http::Response;
+
+    use crate::body::Body;
+
+    pub struct ResponseTag;
+
+    pub trait ResponseKind {
+        #[inline]
+        fn fastedge_error_kind(&self) -> ResponseTag {
+            ResponseTag
+        }
+    }
+
+    impl<E: Into<Response<Body>>> ResponseKind for E {}
+
+    impl ResponseTag {
+        #[inline]
+        pub fn response<E: Into<Response<Body>>>(self, error: E) -> Result<Response<Body>, String> {
+            Ok(error.into())
+        }
+    }
+
+    pub struct AnyhowTag;
+
+    pub trait AnyhowKind {
+        #[inline]
+        fn fastedge_error_kind(&self) -> AnyhowTag {
+            AnyhowTag
+        }
+    }
+
+    #[cfg(feature = "anyhow")]
+    impl AnyhowKind for anyhow::Error {}
+
+    #[cfg(feature = "anyhow")]
+    impl AnyhowTag {
+        #[inline]
+        pub fn response(self, error: anyhow::Error) -> Result<Response<Body>, String> {
+            Ok(super::anyhow_response(&error))
+        }
+    }
+
+    pub struct DisplayTag;
+
+    pub trait DisplayKind {
+        #[inline]
+        fn fastedge_error_kind(&self) -> DisplayTag {
+            DisplayTag
+        }
+    }
+
+    impl<E: Display> DisplayKind for &E {}
+
+    impl DisplayTag {
+        #[inline]
+        pub fn response<E: Display>(self, error: E) -> Result<Response<Body>, String> {
+            Err(error.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn into_response() {
+        let res = HandlerError::new(StatusCode::NOT_FOUND, "no such thing").into_response();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(&res.body()[..], b"no such thing");
+    }
+
+    #[test]
+    fn from_sdk_error() {
+        fn forward() -> Result<(), HandlerError> {
+            Err(crate::Error::Timeout)?
+        }
+        let error = forward().unwrap_err();
+        assert_eq!(error.status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(error.message, "deadline exceeded");
+
+        let res = Response::from(crate::Error::TooManyRedirects(3));
+        assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn from_builder_error() {
+        fn build() -> Result<Response<Body>, HandlerError> {
+            Ok(Response::builder()
+                .header("bad name", "x")
+                .body(Body::empty())?)
+        }
+        assert_eq!(
+            build().unwrap_err().status,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[cfg(feature = "anyhow")]
+    #[test]
+    fn anyhow_status_inference() {
+        use anyhow::Context;
+
+        let error = Err::<(), _>(HandlerError::new(StatusCode::NOT_FOUND, "no such thing"))
+            .context("lookup failed")
+            .unwrap_err();
+        let res = anyhow_response(&error);
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(&res.body()[..], b"no such thing");
+
+        let error = anyhow::Error::new(crate::Error::Timeout).context("calling backend");
+        let res = anyhow_response(&error);
+        assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(&res.body()[..], b"calling backend");
+
+        let res = anyhow_response(&anyhow::anyhow!("boom"));
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,65 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, HeaderValue, Method, Request, Response};
+use bytes::Bytes;
+
+use crate::body::Body;
+
+/// Drop the response body when answering a `HEAD` request.
+///
+/// The `Content-Length` header is set to the length of the dropped body unless the handler has
+/// already provided one, so the client sees the same headers as for the matching `GET` request.
+pub fn strip_body_for_head(req: &Request<Body>, res: Response<Body>) -> Response<Body> {
+    if req.method() != Method::HEAD {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+    if !parts.headers.contains_key(header::CONTENT_LENGTH) {
+        parts
+            .headers
+            .insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+    }
+    let body = Body {
+        content_type: body.content_type,
+        inner: Bytes::new(),
+    };
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn head_body_dropped() {
+        let res = strip_body_for_head(&request(Method::HEAD), Response::new(Body::from("hello")));
+        assert!(res.body().is_empty());
+        assert_eq!(res.headers()[header::CONTENT_LENGTH], "5");
+    }
+
+    #[test]
+    fn content_length_kept() {
+        let res = Response::builder()
+            .header(header::CONTENT_LENGTH, "100")
+            .body(Body::from("hello"))
+            .unwrap();
+        let res = strip_body_for_head(&request(Method::HEAD), res);
+        assert_eq!(res.headers()[header::CONTENT_LENGTH], "100");
+    }
+
+    #[test]
+    fn get_unchanged() {
+        let res = strip_body_for_head(&request(Method::GET), Response::new(Body::from("hello")));
+        assert_eq!(res.body().as_ref(), b"hello");
+        assert!(!res.headers().contains_key(header::CONTENT_LENGTH));
+    }
+}
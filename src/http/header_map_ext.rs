@@ -0,0 +1,91 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::header::AsHeaderName;
+use ::http::HeaderMap;
+
+/// Extension methods for [`HeaderMap`]
+pub trait HeaderMapExt {
+    /// All values of a header as strings, values that are not valid UTF-8 are skipped
+    fn get_all_str<K: AsHeaderName>(&self, name: K) -> Vec<&str>;
+
+    /// Comma-delimited tokens of all values of a header, trimmed and without empty entries.
+    ///
+    /// Commas inside quoted strings do not split.
+    fn get_comma_split<K: AsHeaderName>(&self, name: K) -> Vec<String>;
+}
+
+impl HeaderMapExt for HeaderMap {
+    fn get_all_str<K: AsHeaderName>(&self, name: K) -> Vec<&str> {
+        self.get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect()
+    }
+
+    fn get_comma_split<K: AsHeaderName>(&self, name: K) -> Vec<String> {
+        self.get_all_str(name)
+            .into_iter()
+            .flat_map(|value| split_quoted(value, ','))
+            .collect()
+    }
+}
+
+/// split on `delimiter` outside quoted strings, tokens are trimmed and empty ones dropped
+pub(super) fn split_quoted(value: &str, delimiter: char) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => {
+                tokens.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    tokens.push(&value[start..]);
+    tokens
+        .into_iter()
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ::http::{header, HeaderValue};
+
+    use super::*;
+
+    #[test]
+    fn multi_value_headers() {
+        let mut headers = HeaderMap::new();
+        headers.append(header::ACCEPT, HeaderValue::from_static("text/html, , */*"));
+        headers.append(header::ACCEPT, HeaderValue::from_bytes(b"\xff").unwrap());
+        headers.append(header::ACCEPT, HeaderValue::from_static("a;q=\"1,2\""));
+        assert_eq!(
+            headers.get_all_str(header::ACCEPT),
+            ["text/html, , */*", "a;q=\"1,2\""]
+        );
+        assert_eq!(
+            headers.get_comma_split(header::ACCEPT),
+            ["text/html", "*/*", "a;q=\"1,2\""]
+        );
+        assert!(headers.get_comma_split(header::VARY).is_empty());
+    }
+
+    #[test]
+    fn split_quoted_escapes() {
+        assert_eq!(
+            split_quoted(r#"a="x\";y"; b=2;"#, ';'),
+            [r#"a="x\";y""#, "b=2"]
+        );
+    }
+}
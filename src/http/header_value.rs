@@ -0,0 +1,56 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::HeaderValue;
+
+/// Error returned by [`safe`]
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderError {
+    /// CR or LF at the given byte offset, a header injection attempt
+    #[error("line break at offset {0} in header value")]
+    LineBreak(usize),
+    /// Other control character at the given byte offset
+    #[error("control character at offset {0} in header value")]
+    ControlCharacter(usize),
+}
+
+/// Header value from untrusted input, e.g. a redirect target taken from a query parameter.
+///
+/// CR and LF are reported separately as they are used to inject headers; other control
+/// characters except horizontal tab are rejected as well.
+pub fn safe(value: &str) -> Result<HeaderValue, HeaderError> {
+    for (offset, byte) in value.bytes().enumerate() {
+        match byte {
+            b'\r' | b'\n' => return Err(HeaderError::LineBreak(offset)),
+            b'\t' => {}
+            byte if byte < b' ' || byte == 0x7f => {
+                return Err(HeaderError::ControlCharacter(offset))
+            }
+            _ => {}
+        }
+    }
+    Ok(HeaderValue::from_str(value).expect("header value without control characters"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_values() {
+        assert_eq!(
+            safe("https://example.com/a?b=c").unwrap(),
+            "https://example.com/a?b=c"
+        );
+        assert_eq!(safe("a\tb").unwrap(), "a\tb");
+        assert_eq!(safe("").unwrap(), "");
+    }
+
+    #[test]
+    fn rejected_values() {
+        assert_eq!(safe("/a\r\nSet-Cookie: x"), Err(HeaderError::LineBreak(2)));
+        assert_eq!(safe("\n"), Err(HeaderError::LineBreak(0)));
+        assert_eq!(safe("a\0"), Err(HeaderError::ControlCharacter(1)));
+        assert_eq!(safe("a\x7f"), Err(HeaderError::ControlCharacter(1)));
+    }
+}
@@ -0,0 +1,44 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::HeaderMap;
+
+/// Convert headers to the `(name, value)` pairs of the host interface, in order and with repeated
+/// headers kept.
+///
+/// Values that are not valid UTF-8 are converted lossily instead of failing, so opaque bytes
+/// allowed by [`HeaderValue`](::http::HeaderValue) can't panic the conversion.
+pub(crate) fn to_bindgen(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ::http::{header, HeaderValue};
+
+    use super::*;
+
+    #[test]
+    fn repeated_and_opaque_values() {
+        let mut headers = HeaderMap::new();
+        headers.append(header::SET_COOKIE, HeaderValue::from_static("a=1"));
+        headers.append(header::SET_COOKIE, HeaderValue::from_static("b=2"));
+        headers.insert(header::ETAG, HeaderValue::from_bytes(b"\"\xff\"").unwrap());
+        assert_eq!(
+            to_bindgen(&headers),
+            [
+                ("set-cookie".to_string(), "a=1".to_string()),
+                ("set-cookie".to_string(), "b=2".to_string()),
+                ("etag".to_string(), "\"\u{fffd}\"".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,39 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{HeaderName, Request};
+
+use crate::body::Body;
+
+/// `Idempotency-Key` header name
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Idempotency key sent by the client, if any
+pub fn key(req: &Request<Body>) -> Option<&str> {
+    req.headers()
+        .get(IDEMPOTENCY_KEY)?
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idempotency_key() {
+        let req = Request::builder()
+            .header(IDEMPOTENCY_KEY, " 8e03978e-40d5 ")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(key(&req), Some("8e03978e-40d5"));
+        let req = Request::builder()
+            .header(IDEMPOTENCY_KEY, " ")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(key(&req), None);
+        assert_eq!(key(&Request::new(Body::empty())), None);
+    }
+}
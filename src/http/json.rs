@@ -0,0 +1,87 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, Request};
+use serde::de::DeserializeOwned;
+
+use crate::body::Body;
+
+/// Error type returned by [`parse_json_body`]
+#[derive(thiserror::Error, Debug)]
+pub enum JsonBodyError {
+    /// Request has no `Content-Type` header
+    #[error("missing content type")]
+    MissingContentType,
+    /// Request `Content-Type` is not JSON
+    #[error("content type `{0}` is not json")]
+    UnsupportedContentType(String),
+    /// Request body is not valid JSON for the target type
+    #[error("malformed json body: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+/// Check the request `Content-Type` is JSON (`application/json` or any `+json` type) and
+/// deserialize the request body
+pub fn parse_json_body<T: DeserializeOwned>(req: &Request<Body>) -> Result<T, JsonBodyError> {
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .ok_or(JsonBodyError::MissingContentType)?;
+    let content_type = content_type.to_str().unwrap_or_default();
+    if !is_json(content_type) {
+        return Err(JsonBodyError::UnsupportedContentType(
+            content_type.to_string(),
+        ));
+    }
+
+    Ok(serde_json::from_slice(req.body())?)
+}
+
+pub(super) fn is_json(content_type: &str) -> bool {
+    content_type
+        .parse::<mime::Mime>()
+        .is_ok_and(|mime| mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    fn request(content_type: Option<&str>, body: &'static str) -> Request<Body> {
+        let mut req = Request::builder();
+        if let Some(content_type) = content_type {
+            req = req.header(header::CONTENT_TYPE, content_type);
+        }
+        req.body(Body::from(body)).unwrap()
+    }
+
+    #[test]
+    fn json_body() {
+        let req = request(Some("application/json; charset=utf-8"), r#"{"a": 1}"#);
+        let value: Value = parse_json_body(&req).unwrap();
+        assert_eq!(value["a"], 1);
+        let req = request(Some("application/merge-patch+json"), "[]");
+        assert!(parse_json_body::<Value>(&req).is_ok());
+    }
+
+    #[test]
+    fn errors() {
+        let req = request(None, "{}");
+        assert!(matches!(
+            parse_json_body::<Value>(&req),
+            Err(JsonBodyError::MissingContentType)
+        ));
+        let req = request(Some("text/plain"), "{}");
+        assert!(matches!(
+            parse_json_body::<Value>(&req),
+            Err(JsonBodyError::UnsupportedContentType(content_type)) if content_type == "text/plain"
+        ));
+        let req = request(Some("application/json"), "{");
+        assert!(matches!(
+            parse_json_body::<Value>(&req),
+            Err(JsonBodyError::Malformed(_))
+        ));
+    }
+}
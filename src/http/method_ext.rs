@@ -0,0 +1,36 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::Method;
+
+/// Whether the method is safe, i.e. read-only (`GET`, `HEAD`, `OPTIONS`, `TRACE`)
+pub fn method_is_safe(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Whether repeating the request has the same effect as sending it once: safe methods plus
+/// `PUT` and `DELETE`
+pub fn method_is_idempotent(method: &Method) -> bool {
+    method_is_safe(method) || matches!(*method, Method::PUT | Method::DELETE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn method_properties() {
+        for method in [Method::GET, Method::HEAD, Method::OPTIONS, Method::TRACE] {
+            assert!(method_is_safe(&method) && method_is_idempotent(&method));
+        }
+        for method in [Method::PUT, Method::DELETE] {
+            assert!(!method_is_safe(&method) && method_is_idempotent(&method));
+        }
+        for method in [Method::POST, Method::PATCH, Method::CONNECT] {
+            assert!(!method_is_safe(&method) && !method_is_idempotent(&method));
+        }
+    }
+}
@@ -0,0 +1,107 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, HeaderName, Method, Request};
+
+use crate::body::Body;
+
+/// `X-HTTP-Method-Override` header name
+pub const X_HTTP_METHOD_OVERRIDE: HeaderName = HeaderName::from_static("x-http-method-override");
+
+/// Form field carrying the overridden method
+pub const FORM_FIELD: &str = "_method";
+
+/// Effective request method.
+///
+/// A `POST` request may tunnel `PUT`, `PATCH` or `DELETE` through the `X-HTTP-Method-Override`
+/// header or, for `application/x-www-form-urlencoded` bodies, a `_method` form field. The header
+/// takes precedence. Any other request or override value yields the real method.
+pub fn resolve(req: &Request<Body>) -> Method {
+    if req.method() != Method::POST {
+        return req.method().clone();
+    }
+    let header = req
+        .headers()
+        .get(X_HTTP_METHOD_OVERRIDE)
+        .and_then(|value| value.to_str().ok());
+    header
+        .or_else(|| form_field(req))
+        .and_then(allowed)
+        .unwrap_or(Method::POST)
+}
+
+fn form_field(req: &Request<Body>) -> Option<&str> {
+    let content_type = req.headers().get(header::CONTENT_TYPE)?.to_str().ok()?;
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    if !essence.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+        return None;
+    }
+    std::str::from_utf8(req.body())
+        .ok()?
+        .split('&')
+        .find_map(|pair| match pair.split_once('=') {
+            Some((FORM_FIELD, value)) => Some(value),
+            _ => None,
+        })
+}
+
+fn allowed(method: &str) -> Option<Method> {
+    match method.trim().to_ascii_uppercase().as_str() {
+        "PUT" => Some(Method::PUT),
+        "PATCH" => Some(Method::PATCH),
+        "DELETE" => Some(Method::DELETE),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(
+        method: Method,
+        headers: &[(HeaderName, &str)],
+        body: &'static str,
+    ) -> Request<Body> {
+        let mut req = Request::builder().method(method);
+        for (name, value) in headers {
+            req = req.header(name, *value);
+        }
+        req.body(Body::from(body)).unwrap()
+    }
+
+    const FORM: (HeaderName, &str) = (
+        header::CONTENT_TYPE,
+        "application/x-www-form-urlencoded; charset=utf-8",
+    );
+
+    #[test]
+    fn header_override() {
+        let req = request(Method::POST, &[(X_HTTP_METHOD_OVERRIDE, "delete")], "");
+        assert_eq!(resolve(&req), Method::DELETE);
+        let req = request(
+            Method::POST,
+            &[(X_HTTP_METHOD_OVERRIDE, "PATCH"), FORM],
+            "_method=PUT",
+        );
+        assert_eq!(resolve(&req), Method::PATCH);
+    }
+
+    #[test]
+    fn form_override() {
+        let req = request(Method::POST, &[FORM], "name=a&_method=PUT");
+        assert_eq!(resolve(&req), Method::PUT);
+        let req = request(Method::POST, &[], "_method=PUT");
+        assert_eq!(resolve(&req), Method::POST);
+    }
+
+    #[test]
+    fn ignored_overrides() {
+        let req = request(Method::GET, &[(X_HTTP_METHOD_OVERRIDE, "DELETE")], "");
+        assert_eq!(resolve(&req), Method::GET);
+        let req = request(Method::POST, &[(X_HTTP_METHOD_OVERRIDE, "GET")], "");
+        assert_eq!(resolve(&req), Method::POST);
+        let req = request(Method::POST, &[FORM], "_method=CONNECT");
+        assert_eq!(resolve(&req), Method::POST);
+    }
+}
@@ -0,0 +1,107 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::collections::HashMap;
+
+use ::http::{header, Request};
+use bytes::Bytes;
+
+use crate::body::Body;
+use crate::encoding::url;
+use crate::Error;
+
+/// Request body parsed by [`parse_body`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum BodyData {
+    /// `application/json` or any `+json` body
+    #[cfg(feature = "json")]
+    Json(serde_json::Value),
+    /// `application/x-www-form-urlencoded` body, the last value of a repeated field wins
+    Form(HashMap<String, String>),
+    /// Body of any other or missing content type
+    Raw(Bytes),
+}
+
+/// Parse the request body according to its `Content-Type`.
+///
+/// JSON is only recognized with the `json` feature, otherwise it is returned as
+/// [`BodyData::Raw`]. Malformed JSON fails with [`Error::Serialization`].
+pub fn parse_body(req: &Request<Body>) -> Result<BodyData, Error> {
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+
+    #[cfg(feature = "json")]
+    if super::json::is_json(content_type) {
+        return Ok(BodyData::Json(serde_json::from_slice(req.body())?));
+    }
+    if essence.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+        let form = String::from_utf8_lossy(req.body())
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (
+                    url::decode_form_component(name),
+                    url::decode_form_component(value),
+                )
+            })
+            .collect();
+        return Ok(BodyData::Form(form));
+    }
+    Ok(BodyData::Raw(req.body().inner.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(content_type: Option<&str>, body: &'static str) -> Request<Body> {
+        let mut req = Request::builder();
+        if let Some(content_type) = content_type {
+            req = req.header(header::CONTENT_TYPE, content_type);
+        }
+        req.body(Body::from(body)).unwrap()
+    }
+
+    #[test]
+    fn form() {
+        let req = request(
+            Some("application/x-www-form-urlencoded"),
+            "a=1&b=x+y%21&a=2&&flag",
+        );
+        let BodyData::Form(form) = parse_body(&req).unwrap() else {
+            panic!("not a form");
+        };
+        assert_eq!(form.len(), 3);
+        assert_eq!(form["a"], "2");
+        assert_eq!(form["b"], "x y!");
+        assert_eq!(form["flag"], "");
+    }
+
+    #[test]
+    fn raw() {
+        let req = request(Some("text/plain"), "a=1");
+        assert_eq!(
+            parse_body(&req).unwrap(),
+            BodyData::Raw(Bytes::from_static(b"a=1"))
+        );
+        let req = request(None, "");
+        assert_eq!(parse_body(&req).unwrap(), BodyData::Raw(Bytes::new()));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json() {
+        let req = request(Some("application/vnd.api+json"), r#"{"a": [1]}"#);
+        assert_eq!(
+            parse_body(&req).unwrap(),
+            BodyData::Json(serde_json::json!({"a": [1]}))
+        );
+        let req = request(Some("application/json"), "{");
+        assert!(matches!(parse_body(&req), Err(Error::Serialization(_))));
+    }
+}
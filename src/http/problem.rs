@@ -0,0 +1,187 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, Response, StatusCode};
+use bytes::Bytes;
+use serde_json::{Map, Value};
+
+use crate::body::Body;
+
+/// `application/problem+json` media type
+pub const APPLICATION_PROBLEM_JSON: &str = "application/problem+json";
+
+/// Standard problem members, which are never taken from extensions
+const STANDARD_MEMBERS: [&str; 5] = ["type", "title", "status", "detail", "instance"];
+
+/// RFC 7807 problem details
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// URI reference identifying the problem type, `about:blank` when not set
+    pub problem_type: Option<String>,
+    /// Short summary of the problem type, defaults to the status reason phrase
+    pub title: Option<String>,
+    /// HTTP status code
+    pub status: StatusCode,
+    /// Explanation specific to this occurrence
+    pub detail: Option<String>,
+    /// URI reference identifying this occurrence
+    pub instance: Option<String>,
+    /// Additional members serialized alongside the standard ones, entries named like a standard
+    /// member are ignored
+    pub extensions: Map<String, Value>,
+}
+
+impl Problem {
+    /// Problem with the given status and no other details
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            problem_type: None,
+            title: None,
+            status,
+            detail: None,
+            instance: None,
+            extensions: Map::new(),
+        }
+    }
+
+    /// Set the problem type URI
+    pub fn problem_type(mut self, problem_type: impl Into<String>) -> Self {
+        self.problem_type = Some(problem_type.into());
+        self
+    }
+
+    /// Set the title
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set the detail
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Set the instance URI
+    pub fn instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Add an extension member, names of standard members such as `detail` are ignored
+    pub fn extension(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        let name = name.into();
+        if !STANDARD_MEMBERS.contains(&name.as_str()) {
+            self.extensions.insert(name, value.into());
+        }
+        self
+    }
+
+    /// JSON representation of the problem
+    pub fn to_json(&self) -> Value {
+        let mut object = self.extensions.clone();
+        object.retain(|name, _| !STANDARD_MEMBERS.contains(&name.as_str()));
+        let problem_type = self.problem_type.as_deref().unwrap_or("about:blank");
+        object.insert("type".to_string(), problem_type.into());
+        let title = self
+            .title
+            .as_deref()
+            .or_else(|| self.status.canonical_reason());
+        if let Some(title) = title {
+            object.insert("title".to_string(), title.into());
+        }
+        object.insert("status".to_string(), self.status.as_u16().into());
+        if let Some(detail) = &self.detail {
+            object.insert("detail".to_string(), detail.as_str().into());
+        }
+        if let Some(instance) = &self.instance {
+            object.insert("instance".to_string(), instance.as_str().into());
+        }
+        Value::Object(object)
+    }
+
+    /// Build the `application/problem+json` response
+    pub fn into_response(self) -> Response<Body> {
+        let body = Body {
+            content_type: APPLICATION_PROBLEM_JSON.to_string(),
+            inner: Bytes::from(self.to_json().to_string()),
+        };
+        let mut res = Response::new(body);
+        *res.status_mut() = self.status;
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static(APPLICATION_PROBLEM_JSON),
+        );
+        res
+    }
+}
+
+impl From<Problem> for Response<Body> {
+    fn from(problem: Problem) -> Self {
+        problem.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn defaults() {
+        assert_eq!(
+            Problem::new(StatusCode::NOT_FOUND).to_json(),
+            json!({"type": "about:blank", "title": "Not Found", "status": 404})
+        );
+    }
+
+    #[test]
+    fn members() {
+        let problem = Problem::new(StatusCode::FORBIDDEN)
+            .problem_type("https://example.com/probs/out-of-credit")
+            .title("You do not have enough credit.")
+            .detail("Your current balance is 30, but that costs 50.")
+            .instance("/account/12345/msgs/abc")
+            .extension("balance", 30)
+            .extension("status", 200);
+        assert_eq!(
+            problem.to_json(),
+            json!({
+                "type": "https://example.com/probs/out-of-credit",
+                "title": "You do not have enough credit.",
+                "status": 403,
+                "detail": "Your current balance is 30, but that costs 50.",
+                "instance": "/account/12345/msgs/abc",
+                "balance": 30,
+            })
+        );
+    }
+
+    #[test]
+    fn reserved_extensions() {
+        let mut problem = Problem::new(StatusCode::NOT_FOUND)
+            .extension("detail", 5)
+            .extension("instance", false)
+            .extension("type", "x");
+        assert!(problem.extensions.is_empty());
+        problem.extensions.insert("title".to_string(), 1.into());
+        assert_eq!(
+            problem.to_json(),
+            json!({"type": "about:blank", "title": "Not Found", "status": 404})
+        );
+    }
+
+    #[test]
+    fn response() {
+        let res: Response<Body> = Problem::new(StatusCode::BAD_REQUEST).into();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.headers()[header::CONTENT_TYPE],
+            APPLICATION_PROBLEM_JSON
+        );
+        assert_eq!(res.body().content_type, APPLICATION_PROBLEM_JSON);
+        let body: Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body["status"], 400);
+    }
+}
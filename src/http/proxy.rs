@@ -0,0 +1,303 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, HeaderMap, HeaderName, HeaderValue, Request, Response, Uri};
+
+use super::replace_body;
+use crate::body::Body;
+use crate::{send_request, Error};
+
+/// Send several backend requests and collect the responses in request order.
+///
+/// The outbound HTTP interface is blocking, so requests are currently dispatched one after
+/// another. The API is shaped for concurrent dispatch once the host supports it.
+pub fn fan_out(requests: Vec<Request<Body>>) -> Vec<Result<Response<Body>, Error>> {
+    requests.into_iter().map(send_request).collect()
+}
+
+/// Fetch `req` from the `backend` URI and rewrite the body of a successful response with
+/// `transform`, e.g. to render markdown as HTML.
+///
+/// The request is sent without `Accept-Encoding` so the body reaches `transform` unencoded.
+/// Non-2xx responses are forwarded untouched. Otherwise the response goes through
+/// [`passthrough`], `Content-Type` is taken from the new body, and `Content-Length`, `ETag` and
+/// digest headers describing the old body are removed.
+pub fn transform<F>(
+    mut req: Request<Body>,
+    backend: &str,
+    transform: F,
+) -> Result<Response<Body>, Error>
+where
+    F: FnOnce(Body) -> Result<Body, Error>,
+{
+    *req.uri_mut() = backend.parse::<Uri>().map_err(::http::Error::from)?;
+    req.headers_mut().remove(header::HOST);
+    req.headers_mut().remove(header::ACCEPT_ENCODING);
+
+    let res = passthrough(send_request(req)?);
+    if !res.status().is_success() {
+        return Ok(res);
+    }
+    let (mut parts, body) = res.into_parts();
+    for name in ["etag", "content-md5", "digest", "content-digest"] {
+        parts.headers.remove(name);
+    }
+    let mut res = Response::from_parts(parts, Body::empty());
+    replace_body(&mut res, transform(body)?);
+    Ok(res)
+}
+
+/// Append `1.1 <pseudonym>` to the `Via` header of an outbound request or a returned response,
+/// e.g. `proxy::append_via(req.headers_mut(), "fastedge")`.
+///
+/// Existing `Via` values are kept in front and combined into one header. A pseudonym that isn't a
+/// valid header value is ignored.
+pub fn append_via(headers: &mut HeaderMap, pseudonym: &str) {
+    let mut via = headers
+        .get_all(header::VIA)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    if !via.is_empty() {
+        via.push_str(", ");
+    }
+    via.push_str("1.1 ");
+    via.push_str(pseudonym);
+    if let Ok(via) = HeaderValue::from_str(&via) {
+        headers.insert(header::VIA, via);
+    }
+}
+
+/// Headers removed by [`strip_sensitive`]
+pub const SENSITIVE_HEADERS: [HeaderName; 4] = [
+    header::COOKIE,
+    header::AUTHORIZATION,
+    header::PROXY_AUTHORIZATION,
+    header::SET_COOKIE,
+];
+
+/// Remove client credentials (`Cookie`, `Authorization`, `Proxy-Authorization`, `Set-Cookie`)
+/// before forwarding a request to a third-party backend
+pub fn strip_sensitive(req: &mut Request<Body>) {
+    strip_headers(req, &SENSITIVE_HEADERS);
+}
+
+/// Remove all values of the listed headers before forwarding a request
+pub fn strip_headers(req: &mut Request<Body>, names: &[HeaderName]) {
+    for name in names {
+        req.headers_mut().remove(name);
+    }
+}
+
+/// Hop-by-hop headers that apply to a single connection and must not be forwarded
+const HOP_BY_HOP: [HeaderName; 7] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    HeaderName::from_static("proxy-connection"),
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// Forward a backend response to the client unchanged.
+///
+/// Status, body bytes and all end-to-end headers, including repeated ones, are kept. The
+/// hop-by-hop headers `Connection`, `Keep-Alive`, `Proxy-Connection`, `TE`, `Trailer`,
+/// `Transfer-Encoding` and `Upgrade` are dropped together with any header listed in `Connection`.
+/// The body content type is aligned with the `Content-Type` header.
+pub fn passthrough(res: Response<Body>) -> Response<Body> {
+    let (mut parts, mut body) = res.into_parts();
+
+    let listed = parts
+        .headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect::<Vec<HeaderName>>();
+    for name in listed.iter().chain(HOP_BY_HOP.iter()) {
+        parts.headers.remove(name);
+    }
+
+    if let Some(content_type) = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    {
+        body.content_type = content_type.to_string();
+    }
+    Response::from_parts(parts, body)
+}
+
+/// Rewrite a backend `Location` header so redirects keep the client on the edge.
+///
+/// An absolute location under `from_base` (e.g. `https://origin.internal/api`) is moved under
+/// `to_base` (e.g. `https://edge.example.com/v1`). A path-only location under the path of
+/// `from_base` gets the path of `to_base` instead. Other locations are left as they are.
+pub fn rewrite_location(res: &mut Response<Body>, from_base: &str, to_base: &str) {
+    let Some(location) = res
+        .headers()
+        .get(header::LOCATION)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return;
+    };
+
+    let rewritten = if location.starts_with('/') && !location.starts_with("//") {
+        // paths are case-sensitive
+        let from_path = base_path(from_base);
+        let to_path = base_path(to_base);
+        strip_base(location, &from_path, false).map(|rest| format!("{}{}", to_path, rest))
+    } else {
+        // scheme and host are case-insensitive
+        strip_base(location, from_base.trim_end_matches('/'), true)
+            .map(|rest| format!("{}{}", to_base.trim_end_matches('/'), rest))
+    };
+
+    if let Some(value) = rewritten.and_then(|value| HeaderValue::from_str(&value).ok()) {
+        res.headers_mut().insert(header::LOCATION, value);
+    }
+}
+
+/// path of a base URL without the trailing slash, empty for the root
+fn base_path(base: &str) -> String {
+    base.parse::<Uri>()
+        .map(|uri| uri.path().trim_end_matches('/').to_string())
+        .unwrap_or_default()
+}
+
+/// remainder of `value` after `base`, only at a path boundary
+fn strip_base<'a>(value: &'a str, base: &str, ignore_case: bool) -> Option<&'a str> {
+    let prefix = value.get(..base.len())?;
+    let matches = if ignore_case {
+        prefix.eq_ignore_ascii_case(base)
+    } else {
+        prefix == base
+    };
+    if !matches {
+        return None;
+    }
+    let rest = &value[base.len()..];
+    match rest.chars().next() {
+        None | Some('/' | '?' | '#') => Some(rest),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passthrough_drops_hop_by_hop() {
+        let res = Response::builder()
+            .status(201)
+            .header(header::CONNECTION, "keep-alive, X-Internal")
+            .header("keep-alive", "timeout=5")
+            .header(header::TRANSFER_ENCODING, "chunked")
+            .header("x-internal", "1")
+            .header(header::SET_COOKIE, "a=1")
+            .header(header::SET_COOKIE, "b=2")
+            .header(header::CONTENT_TYPE, "image/png")
+            .body(Body::from("data"))
+            .unwrap();
+        let res = passthrough(res);
+        assert_eq!(res.status(), 201);
+        assert_eq!(res.body().as_ref(), b"data");
+        assert_eq!(res.body().content_type, "image/png");
+        let names = res
+            .headers()
+            .keys()
+            .map(HeaderName::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["set-cookie", "content-type"]);
+        assert_eq!(res.headers().get_all(header::SET_COOKIE).iter().count(), 2);
+    }
+
+    fn rewritten(location: &str) -> String {
+        let mut res = Response::builder()
+            .header(header::LOCATION, location)
+            .body(Body::empty())
+            .unwrap();
+        rewrite_location(
+            &mut res,
+            "https://origin.internal/api/",
+            "https://edge.example.com/v1",
+        );
+        res.headers()[header::LOCATION]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn rewrite_locations() {
+        assert_eq!(
+            rewritten("HTTPS://Origin.Internal/api/items?page=2"),
+            "https://edge.example.com/v1/items?page=2"
+        );
+        assert_eq!(
+            rewritten("https://origin.internal/api"),
+            "https://edge.example.com/v1"
+        );
+        assert_eq!(rewritten("/api/items#top"), "/v1/items#top");
+    }
+
+    #[test]
+    fn unrelated_locations_kept() {
+        for location in [
+            "https://origin.internal/apis",
+            "https://other.example.com/api/items",
+            "/API/items",
+            "/apis",
+            "//origin.internal/api/items",
+        ] {
+            assert_eq!(rewritten(location), location);
+        }
+    }
+
+    #[test]
+    fn strip_credentials() {
+        let mut req = Request::builder()
+            .header(header::COOKIE, "session=1")
+            .header(header::AUTHORIZATION, "Bearer x")
+            .header(header::AUTHORIZATION, "Basic y")
+            .header(header::ACCEPT, "*/*")
+            .header("x-api-key", "k")
+            .body(Body::empty())
+            .unwrap();
+        strip_sensitive(&mut req);
+        assert!(!req.headers().contains_key(header::COOKIE));
+        assert!(!req.headers().contains_key(header::AUTHORIZATION));
+        assert!(req.headers().contains_key(header::ACCEPT));
+
+        strip_headers(&mut req, &[HeaderName::from_static("x-api-key")]);
+        assert_eq!(req.headers().len(), 1);
+    }
+
+    #[test]
+    fn via() {
+        let mut headers = HeaderMap::new();
+        append_via(&mut headers, "fastedge");
+        assert_eq!(headers[header::VIA], "1.1 fastedge");
+
+        let mut headers = HeaderMap::new();
+        headers.append(header::VIA, HeaderValue::from_static("1.0 fred"));
+        headers.append(header::VIA, HeaderValue::from_static("1.1 p.example.net"));
+        append_via(&mut headers, "fastedge");
+        assert_eq!(headers.get_all(header::VIA).iter().count(), 1);
+        assert_eq!(
+            headers[header::VIA],
+            "1.0 fred, 1.1 p.example.net, 1.1 fastedge"
+        );
+
+        append_via(&mut headers, "bad\n");
+        assert_eq!(
+            headers[header::VIA],
+            "1.0 fred, 1.1 p.example.net, 1.1 fastedge"
+        );
+    }
+}
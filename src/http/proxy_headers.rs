@@ -0,0 +1,131 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::net::{IpAddr, SocketAddr};
+
+use ::http::uri::Scheme;
+use ::http::{HeaderName, Request};
+
+use crate::body::Body;
+
+/// `X-Forwarded-For` header name
+pub const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+/// `X-Forwarded-Proto` header name
+pub const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// Client IP address taken from the leftmost valid `X-Forwarded-For` entry.
+///
+/// Entries which are not IP addresses (e.g. `unknown` or obfuscated values) are skipped and
+/// entries with a port (`1.2.3.4:5678`, `[::1]:80`) are accepted. Only request headers are
+/// inspected, so the value is as trustworthy as the proxies in front of the application; see
+/// [`client_ip_with_config`] for a spoofing-resistant lookup.
+pub fn client_ip(req: &Request<Body>) -> Option<IpAddr> {
+    forwarded_for(req).into_iter().next()
+}
+
+/// Proxies in front of the application whose `X-Forwarded-For` entries are trusted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClientIpConfig {
+    /// Number of trusted proxies, each appending one `X-Forwarded-For` entry
+    pub trusted_hops: usize,
+}
+
+/// Client IP address as seen by the outermost trusted proxy.
+///
+/// Entries are appended by each proxy, so the client is the `trusted_hops`-th entry counted from
+/// the right; anything further left may be spoofed by the client and is ignored. Returns `None`
+/// when no hops are trusted or the selected entry is not an IP address. With fewer entries than
+/// trusted hops the leftmost entry is used.
+pub fn client_ip_with_config(req: &Request<Body>, config: &ClientIpConfig) -> Option<IpAddr> {
+    if config.trusted_hops == 0 {
+        return None;
+    }
+    let entries = req
+        .headers()
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect::<Vec<&str>>();
+    let index = entries.len().saturating_sub(config.trusted_hops);
+    parse_ip(entries.get(index)?)
+}
+
+/// Protocol the client used to connect, taken from `X-Forwarded-Proto`
+pub fn forwarded_proto(req: &Request<Body>) -> Option<Scheme> {
+    let value = req.headers().get(X_FORWARDED_PROTO)?.to_str().ok()?;
+    let proto = value.split(',').next()?.trim().to_ascii_lowercase();
+    proto.parse().ok()
+}
+
+/// All valid `X-Forwarded-For` addresses in order, client first
+pub(crate) fn forwarded_for(req: &Request<Body>) -> Vec<IpAddr> {
+    req.headers()
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(parse_ip)
+        .collect()
+}
+
+fn parse_ip(entry: &str) -> Option<IpAddr> {
+    let entry = entry.trim();
+    entry
+        .parse::<IpAddr>()
+        .or_else(|_| entry.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(forwarded_for: &[&str]) -> Request<Body> {
+        let mut req = Request::builder();
+        for value in forwarded_for {
+            req = req.header(X_FORWARDED_FOR, *value);
+        }
+        req.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn leftmost_client_ip() {
+        let req = request(&["unknown, 203.0.113.7:5678", "[2001:db8::1]:80"]);
+        assert_eq!(client_ip(&req), "203.0.113.7".parse().ok());
+        assert_eq!(
+            forwarded_for(&req),
+            [
+                "203.0.113.7".parse::<IpAddr>().unwrap(),
+                "2001:db8::1".parse().unwrap()
+            ]
+        );
+        assert_eq!(client_ip(&request(&[])), None);
+    }
+
+    #[test]
+    fn trusted_hops() {
+        let req = request(&["1.1.1.1, 2.2.2.2", "3.3.3.3"]);
+        let ip = |trusted_hops| client_ip_with_config(&req, &ClientIpConfig { trusted_hops });
+        assert_eq!(ip(0), None);
+        assert_eq!(ip(1), "3.3.3.3".parse().ok());
+        assert_eq!(ip(2), "2.2.2.2".parse().ok());
+        assert_eq!(ip(5), "1.1.1.1".parse().ok());
+
+        let req = request(&["spoofed, unknown"]);
+        assert_eq!(
+            client_ip_with_config(&req, &ClientIpConfig { trusted_hops: 1 }),
+            None
+        );
+    }
+
+    #[test]
+    fn proto() {
+        let req = Request::builder()
+            .header(X_FORWARDED_PROTO, "HTTPS, http")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(forwarded_proto(&req), Some(Scheme::HTTPS));
+        assert_eq!(forwarded_proto(&request(&[])), None);
+    }
+}
@@ -0,0 +1,204 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::ops::Range;
+
+use ::http::HeaderValue;
+use bytes::{BufMut, BytesMut};
+
+use crate::body::Body;
+
+/// Error type returned by [`parse`]
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeError {
+    /// `Range` header is not a valid `bytes` range, it should be ignored
+    #[error("malformed range header")]
+    Malformed,
+    /// None of the requested ranges overlap the content, or more than [`MAX_RANGES`] are
+    /// requested, respond with 416
+    #[error("range not satisfiable")]
+    Unsatisfiable,
+}
+
+/// Maximum number of ranges accepted in one `Range` header, above it [`parse`] returns
+/// [`RangeError::Unsatisfiable`] rather than building a huge multipart response
+pub const MAX_RANGES: usize = 16;
+
+/// Parse a `Range` header value into the byte ranges of content of length `len`.
+///
+/// Both `start-end`, open `start-` and suffix `-length` ranges are supported. Ranges which do not
+/// overlap the content are dropped, and if none remains [`RangeError::Unsatisfiable`] is returned.
+/// Overlapping and adjacent ranges are merged, so the result is sorted and disjoint.
+pub fn parse(value: &HeaderValue, len: usize) -> Result<Vec<Range<usize>>, RangeError> {
+    let value = value.to_str().map_err(|_| RangeError::Malformed)?;
+    let (unit, specs) = value.split_once('=').ok_or(RangeError::Malformed)?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return Err(RangeError::Malformed);
+    }
+
+    if specs.split(',').count() > MAX_RANGES {
+        return Err(RangeError::Unsatisfiable);
+    }
+
+    let mut ranges = vec![];
+    for spec in specs.split(',') {
+        let (start, end) = spec.split_once('-').ok_or(RangeError::Malformed)?;
+        let (start, end) = (start.trim(), end.trim());
+        if start.is_empty() {
+            let suffix = parse_position(end)?;
+            if suffix > 0 && len > 0 {
+                ranges.push(len.saturating_sub(suffix)..len);
+            }
+            continue;
+        }
+
+        let start = parse_position(start)?;
+        let last = if end.is_empty() {
+            len.saturating_sub(1)
+        } else {
+            let end = parse_position(end)?;
+            if end < start {
+                return Err(RangeError::Malformed);
+            }
+            end.min(len.saturating_sub(1))
+        };
+        if start < len {
+            ranges.push(start..last + 1);
+        }
+    }
+
+    if ranges.is_empty() {
+        return Err(RangeError::Unsatisfiable);
+    }
+    Ok(merge(ranges))
+}
+
+fn merge(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// `Content-Range` header value for `range` of content of length `len`
+pub fn content_range(range: &Range<usize>, len: usize) -> HeaderValue {
+    HeaderValue::from_str(&format!("bytes {}-{}/{}", range.start, range.end - 1, len))
+        .expect("content-range is visible ASCII")
+}
+
+/// `multipart/byteranges` body with one part per range of `bytes`, for a `206 Partial Content`
+/// response to a multi-range request.
+///
+/// Each part carries `content_type` and its own `Content-Range`. The body content type is
+/// `multipart/byteranges` with `boundary`, which must not occur in `bytes`.
+pub fn multipart_body(
+    bytes: &[u8],
+    ranges: &[Range<usize>],
+    content_type: &str,
+    boundary: &str,
+) -> Body {
+    let mut inner = BytesMut::new();
+    for range in ranges {
+        inner.put_slice(
+            format!(
+                "--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                boundary,
+                content_type,
+                range.start,
+                range.end - 1,
+                bytes.len()
+            )
+            .as_bytes(),
+        );
+        inner.put_slice(&bytes[range.clone()]);
+        inner.put_slice(b"\r\n");
+    }
+    inner.put_slice(format!("--{}--\r\n", boundary).as_bytes());
+    Body {
+        content_type: format!("multipart/byteranges; boundary={}", boundary),
+        inner: inner.freeze(),
+    }
+}
+
+fn parse_position(value: &str) -> Result<usize, RangeError> {
+    value.parse().map_err(|_| RangeError::Malformed)
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::*;
+
+    fn parse_str(value: &str, len: usize) -> Result<Vec<Range<usize>>, RangeError> {
+        parse(&HeaderValue::from_str(value).unwrap(), len)
+    }
+
+    #[test]
+    fn single_ranges() {
+        assert_eq!(parse_str("bytes=0-4", 10), Ok(vec![0..5]));
+        assert_eq!(parse_str("bytes=5-", 10), Ok(vec![5..10]));
+        assert_eq!(parse_str("bytes=-3", 10), Ok(vec![7..10]));
+        assert_eq!(parse_str("bytes=8-100", 10), Ok(vec![8..10]));
+        assert_eq!(parse_str("bytes=-100", 10), Ok(vec![0..10]));
+        assert_eq!(parse_str("Bytes = 1-1", 10), Ok(vec![1..2]));
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(parse_str("items=0-4", 10), Err(RangeError::Malformed));
+        assert_eq!(parse_str("bytes=4-2", 10), Err(RangeError::Malformed));
+        assert_eq!(parse_str("bytes=a-b", 10), Err(RangeError::Malformed));
+        assert_eq!(parse_str("bytes=5", 10), Err(RangeError::Malformed));
+        assert_eq!(parse_str("bytes 0-4", 10), Err(RangeError::Malformed));
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        assert_eq!(parse_str("bytes=10-", 10), Err(RangeError::Unsatisfiable));
+        assert_eq!(parse_str("bytes=-0", 10), Err(RangeError::Unsatisfiable));
+        assert_eq!(parse_str("bytes=0-", 0), Err(RangeError::Unsatisfiable));
+        assert_eq!(parse_str("bytes=-5", 0), Err(RangeError::Unsatisfiable));
+    }
+
+    #[test]
+    fn overlapping_ranges_merged() {
+        assert_eq!(parse_str("bytes=0-4,2-6", 10), Ok(vec![0..7]));
+        assert_eq!(parse_str("bytes=5-6,0-1", 10), Ok(vec![0..2, 5..7]));
+        assert_eq!(parse_str("bytes=0-1,2-3", 10), Ok(vec![0..4]));
+        assert_eq!(parse_str("bytes=0-9,3-4,-2", 10), Ok(vec![0..10]));
+        assert_eq!(parse_str("bytes=0-0,20-30", 10), Ok(vec![0..1]));
+    }
+
+    #[test]
+    fn too_many_ranges() {
+        let specs = vec!["0-0"; MAX_RANGES].join(",");
+        assert_eq!(parse_str(&format!("bytes={}", specs), 10), Ok(vec![0..1]));
+        let specs = vec!["0-0"; MAX_RANGES + 1].join(",");
+        assert_eq!(
+            parse_str(&format!("bytes={}", specs), 10),
+            Err(RangeError::Unsatisfiable)
+        );
+    }
+
+    #[test]
+    fn content_range_value() {
+        assert_eq!(content_range(&(2..5), 10), "bytes 2-4/10");
+    }
+
+    #[test]
+    fn multipart() {
+        let body = multipart_body(b"0123456789", &[0..2, 8..10], "text/plain", "b");
+        assert_eq!(body.content_type, "multipart/byteranges; boundary=b");
+        assert_eq!(
+            &body.inner[..],
+            b"--b\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\r\n\
+              --b\r\nContent-Type: text/plain\r\nContent-Range: bytes 8-9/10\r\n\r\n89\r\n\
+              --b--\r\n"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::uri::Scheme;
+use ::http::{header, HeaderValue, Method, Request, Response, StatusCode, Uri};
+
+use super::{error_response, RequestExt};
+use crate::body::Body;
+
+/// Redirect a request made over plain HTTP to the same URL on HTTPS, `None` when it is already
+/// secure, see [`RequestExt::is_secure`].
+///
+/// `GET` and `HEAD` get `301 Moved Permanently`, other methods `308 Permanent Redirect` so the
+/// method and body are kept. The host is taken from the request URI or the `Host` header, and any
+/// explicit port is dropped. Without a host there is no URL to redirect to, so the request is
+/// answered with `400 Bad Request`.
+pub fn enforce(req: &Request<Body>) -> Option<Response<Body>> {
+    if req.is_secure() {
+        return None;
+    }
+
+    let host = match req.uri().host() {
+        Some(host) => Some(host.to_string()),
+        None => req
+            .headers()
+            .get(header::HOST)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|host| host.parse::<Uri>().ok())
+            .and_then(|uri| uri.host().map(str::to_string)),
+    };
+    let location = host.and_then(|host| {
+        Uri::builder()
+            .scheme(Scheme::HTTPS)
+            .authority(host)
+            .path_and_query(req.uri().path_and_query().map_or("/", |pq| pq.as_str()))
+            .build()
+            .ok()
+    });
+    let Some(location) = location.and_then(|uri| HeaderValue::from_str(&uri.to_string()).ok())
+    else {
+        return Some(error_response(StatusCode::BAD_REQUEST, "missing host\n"));
+    };
+
+    let status = if req.method() == Method::GET || req.method() == Method::HEAD {
+        StatusCode::MOVED_PERMANENTLY
+    } else {
+        StatusCode::PERMANENT_REDIRECT
+    };
+    let mut res = Response::new(Body::empty());
+    *res.status_mut() = status;
+    res.headers_mut().insert(header::LOCATION, location);
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::proxy_headers::X_FORWARDED_PROTO;
+
+    #[test]
+    fn redirects() {
+        let req = Request::builder()
+            .uri("http://example.com:8080/a?b=c")
+            .body(Body::empty())
+            .unwrap();
+        let res = enforce(&req).unwrap();
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(res.headers()[header::LOCATION], "https://example.com/a?b=c");
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/form")
+            .header(header::HOST, "example.com:80")
+            .body(Body::empty())
+            .unwrap();
+        let res = enforce(&req).unwrap();
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(res.headers()[header::LOCATION], "https://example.com/form");
+    }
+
+    #[test]
+    fn secure_requests_pass() {
+        let req = Request::builder()
+            .uri("https://example.com/")
+            .body(Body::empty())
+            .unwrap();
+        assert!(enforce(&req).is_none());
+        let req = Request::builder()
+            .uri("/")
+            .header(X_FORWARDED_PROTO, "https")
+            .body(Body::empty())
+            .unwrap();
+        assert!(enforce(&req).is_none());
+    }
+
+    #[test]
+    fn missing_host() {
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        assert_eq!(enforce(&req).unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+}
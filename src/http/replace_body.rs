@@ -0,0 +1,50 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, HeaderValue, Response};
+
+use crate::body::Body;
+
+/// Swap the response body keeping status and headers, returning the previous body.
+///
+/// `Content-Type` is set from the new body and a stale `Content-Length` is removed.
+pub fn replace_body(res: &mut Response<Body>, new: impl Into<Body>) -> Body {
+    let old = res.body_mut().replace(new);
+    res.headers_mut().remove(header::CONTENT_LENGTH);
+    match HeaderValue::from_str(&res.body().content_type) {
+        Ok(content_type) => {
+            res.headers_mut().insert(header::CONTENT_TYPE, content_type);
+        }
+        Err(_) => {
+            res.headers_mut().remove(header::CONTENT_TYPE);
+        }
+    }
+    old
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace() {
+        let mut res = Response::builder()
+            .header(header::CONTENT_LENGTH, "3")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .header(header::ETAG, "\"x\"")
+            .body(Body::from("old"))
+            .unwrap();
+        let old = replace_body(
+            &mut res,
+            Body::from("{}").with_content_type("application/json"),
+        );
+        assert_eq!(old.as_ref(), b"old");
+        assert_eq!(res.body().as_ref(), b"{}");
+        assert!(!res.headers().contains_key(header::CONTENT_LENGTH));
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(res.headers()[header::ETAG], "\"x\"");
+
+        replace_body(&mut res, Body::empty().with_content_type("bad\n"));
+        assert!(!res.headers().contains_key(header::CONTENT_TYPE));
+    }
+}
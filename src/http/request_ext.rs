@@ -0,0 +1,257 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::uri::{Authority, Scheme};
+use ::http::{header, Request, StatusCode, Uri};
+
+use super::proxy_headers::{forwarded_proto, X_FORWARDED_PROTO};
+use super::HandlerError;
+use crate::body::Body;
+use crate::encoding::url;
+use crate::Error;
+
+/// Extension methods for inbound [`Request`]
+pub trait RequestExt {
+    /// Absolute request URL.
+    ///
+    /// The incoming URI is often path-only, in that case the authority is taken from the `Host`
+    /// header and the scheme from `X-Forwarded-Proto`, defaulting to `http`. Returns `None` when
+    /// no authority is known.
+    fn full_url(&self) -> Option<Uri>;
+
+    /// Whether the client sent a body, an empty body is still a body.
+    ///
+    /// Requests built by hand are treated as having a body.
+    fn has_body(&self) -> bool;
+
+    /// Percent-decoded non-empty path segments, an encoded slash (`%2F`) stays inside its segment
+    fn path_segments(&self) -> Vec<String>;
+
+    /// Copy of the request sent to `backend_uri` instead, for forwarding with a rewritten target.
+    ///
+    /// Method, version, headers and body are kept; `Host` is removed so
+    /// [`send_request`](crate::send_request) sets it for the new target. Extensions are not copied.
+    fn to_backend_request(&self, backend_uri: &str) -> Result<Request<Body>, Error>;
+
+    /// Reject a request whose `Content-Type` is not `expected` (e.g. `application/json`) with a
+    /// `415 Unsupported Media Type` error, see [`HandlerError::into_response`].
+    ///
+    /// Type and subtype are compared case-insensitively and parameters such as `charset` are
+    /// ignored. A missing `Content-Type` is a mismatch.
+    fn require_content_type(&self, expected: &str) -> Result<(), HandlerError>;
+
+    /// Scheme the client used, from the request URI or else the first `X-Forwarded-Proto` entry.
+    ///
+    /// `None` when neither is present.
+    fn scheme(&self) -> Option<&str>;
+
+    /// Whether the client connected over HTTPS, see [`scheme`](RequestExt::scheme)
+    fn is_secure(&self) -> bool;
+}
+
+/// Request extension marking a request received without a body
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NoBody;
+
+impl RequestExt for Request<Body> {
+    fn full_url(&self) -> Option<Uri> {
+        let uri = self.uri();
+        let authority = match uri.authority() {
+            Some(authority) => authority.clone(),
+            None => {
+                let host = self.headers().get(header::HOST)?.to_str().ok()?;
+                host.parse::<Authority>().ok()?
+            }
+        };
+        let scheme = uri
+            .scheme()
+            .cloned()
+            .or_else(|| forwarded_proto(self))
+            .unwrap_or(Scheme::HTTP);
+        let path_and_query = uri.path_and_query().map_or("/", |pq| pq.as_str());
+
+        Uri::builder()
+            .scheme(scheme)
+            .authority(authority)
+            .path_and_query(path_and_query)
+            .build()
+            .ok()
+    }
+
+    fn has_body(&self) -> bool {
+        self.extensions().get::<NoBody>().is_none()
+    }
+
+    fn path_segments(&self) -> Vec<String> {
+        self.uri()
+            .path()
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(url::decode)
+            .collect()
+    }
+
+    fn to_backend_request(&self, backend_uri: &str) -> Result<Request<Body>, Error> {
+        let mut req = Request::builder()
+            .method(self.method().clone())
+            .uri(backend_uri)
+            .version(self.version())
+            .body(self.body().clone())?;
+        *req.headers_mut() = self.headers().clone();
+        req.headers_mut().remove(header::HOST);
+        Ok(req)
+    }
+
+    fn require_content_type(&self, expected: &str) -> Result<(), HandlerError> {
+        let essence = |content_type: &str| {
+            content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase()
+        };
+        let matches = self
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| essence(content_type) == essence(expected));
+        if !matches {
+            return Err(HandlerError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("expected content type {}\n", essence(expected)),
+            ));
+        }
+        Ok(())
+    }
+
+    fn scheme(&self) -> Option<&str> {
+        if let Some(scheme) = self.uri().scheme_str() {
+            return Some(scheme);
+        }
+        let proto = self.headers().get(X_FORWARDED_PROTO)?.to_str().ok()?;
+        proto
+            .split(',')
+            .next()
+            .map(str::trim)
+            .filter(|proto| !proto.is_empty())
+    }
+
+    fn is_secure(&self) -> bool {
+        self.scheme()
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("https"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_content_type(content_type: &str) -> Request<Body> {
+        Request::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn require_content_type() {
+        let req = with_content_type("Application/JSON; charset=utf-8");
+        assert_eq!(req.require_content_type("application/json"), Ok(()));
+
+        let error = with_content_type("text/plain")
+            .require_content_type("application/json")
+            .unwrap_err();
+        assert_eq!(error.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(error.message, "expected content type application/json\n");
+
+        let error = Request::new(Body::empty())
+            .require_content_type("application/json")
+            .unwrap_err();
+        assert_eq!(error.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn full_url() {
+        let req = Request::builder()
+            .uri("/a/b?q=1")
+            .header(header::HOST, "example.com")
+            .header(X_FORWARDED_PROTO, "https")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(req.full_url().unwrap(), "https://example.com/a/b?q=1");
+
+        let req = Request::builder()
+            .uri("/")
+            .header(header::HOST, "example.com:8080")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(req.full_url().unwrap(), "http://example.com:8080/");
+
+        let req = Request::builder()
+            .uri("https://origin.test/x")
+            .header(header::HOST, "example.com")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(req.full_url().unwrap(), "https://origin.test/x");
+
+        assert!(Request::new(Body::empty()).full_url().is_none());
+    }
+
+    #[test]
+    fn path_segments() {
+        let req = Request::builder()
+            .uri("//files/a%20b//c%2Fd/?q=1")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(req.path_segments(), ["files", "a b", "c/d"]);
+        assert!(Request::new(Body::empty()).path_segments().is_empty());
+    }
+
+    #[test]
+    fn to_backend_request() {
+        let req = Request::builder()
+            .method(::http::Method::PUT)
+            .uri("/upload")
+            .header(header::HOST, "example.com")
+            .header("x-custom", "1")
+            .body(Body::from("data"))
+            .unwrap();
+        let backend = req
+            .to_backend_request("https://backend.test/upload")
+            .unwrap();
+        assert_eq!(backend.method(), ::http::Method::PUT);
+        assert_eq!(backend.uri(), "https://backend.test/upload");
+        assert!(backend.headers().get(header::HOST).is_none());
+        assert_eq!(backend.headers()["x-custom"], "1");
+        assert_eq!(backend.body().as_ref(), b"data");
+        assert!(req.to_backend_request("not a uri").is_err());
+    }
+
+    #[test]
+    fn scheme() {
+        let req = Request::builder()
+            .uri("https://example.com/")
+            .header(X_FORWARDED_PROTO, "http")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(req.scheme(), Some("https"));
+        assert!(req.is_secure());
+
+        let req = Request::builder()
+            .uri("/")
+            .header(X_FORWARDED_PROTO, "HTTPS, http")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(req.scheme(), Some("HTTPS"));
+        assert!(req.is_secure());
+
+        let req = Request::builder()
+            .uri("/")
+            .header(X_FORWARDED_PROTO, "")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(req.scheme(), None);
+        assert!(!req.is_secure());
+    }
+}
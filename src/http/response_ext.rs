@@ -0,0 +1,32 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{Response, StatusCode};
+
+use crate::body::Body;
+
+/// Extension methods for backend [`Response`]
+pub trait ResponseExt {
+    /// Whether a conditional request was answered with `304 Not Modified`, meaning the cached
+    /// copy is still valid
+    fn is_not_modified(&self) -> bool;
+}
+
+impl ResponseExt for Response<Body> {
+    fn is_not_modified(&self) -> bool {
+        self.status() == StatusCode::NOT_MODIFIED
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_modified() {
+        let mut res = Response::new(Body::empty());
+        assert!(!res.is_not_modified());
+        *res.status_mut() = StatusCode::NOT_MODIFIED;
+        assert!(res.is_not_modified());
+    }
+}
@@ -0,0 +1,57 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::time::Duration;
+
+use ::http::HeaderValue;
+
+use crate::time::{now, parse_http_date};
+
+/// Parse a `Retry-After` header value into the delay before the next attempt.
+///
+/// Both delta-seconds (`120`) and HTTP-date values are accepted, a date in the past yields a zero
+/// delay. Returns `None` for malformed values.
+pub fn parse(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+    if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+        return value.parse().ok().map(Duration::from_secs);
+    }
+    let at = parse_http_date(value)?;
+    Some(at.duration_since(now()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::format_http_date;
+
+    #[test]
+    fn delta_seconds() {
+        assert_eq!(
+            parse(&HeaderValue::from_static("120")),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse(&HeaderValue::from_static(" 0 ")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse(&HeaderValue::from_static("-1")), None);
+        assert_eq!(parse(&HeaderValue::from_static("1.5")), None);
+        assert_eq!(parse(&HeaderValue::from_static("")), None);
+    }
+
+    #[test]
+    fn http_date() {
+        let past = HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(parse(&past), Some(Duration::ZERO));
+        let future = format_http_date(now() + Duration::from_secs(3600)).unwrap();
+        let delay = parse(&HeaderValue::from_str(&future).unwrap()).unwrap();
+        assert!(delay > Duration::from_secs(3500) && delay <= Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn out_of_range_date() {
+        let value = HeaderValue::from_static("Sun, 06 Nov 400000000000 08:49:37 GMT");
+        assert_eq!(parse(&value), None);
+    }
+}
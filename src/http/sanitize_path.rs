@@ -0,0 +1,95 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use crate::encoding::url;
+
+/// Error returned by [`sanitize_path`]
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathError {
+    /// A `..` segment, possibly percent-encoded
+    #[error("path traversal")]
+    Traversal,
+    /// A NUL byte, possibly percent-encoded
+    #[error("NUL byte in path")]
+    NullByte,
+    /// A `%`, `?` or `#` left after decoding, e.g. from double encoding
+    #[error("reserved character in path")]
+    ReservedCharacter,
+    /// Nothing left after normalization
+    #[error("empty path")]
+    Empty,
+}
+
+/// Normalize a request path for joining onto a backend path or object key.
+///
+/// The path is percent-decoded, `.` and empty segments are dropped and the result has no leading
+/// slash. `..` segments and NUL bytes are rejected rather than resolved; `\` counts as a separator
+/// so `..\` is caught as well. Decoded `%`, `?` and `#` are rejected too, so the result can't
+/// smuggle a query, a fragment or a second round of decoding into a backend URL.
+pub fn sanitize_path(path: &str) -> Result<String, PathError> {
+    let decoded = url::decode(path);
+    if decoded.contains('\0') {
+        return Err(PathError::NullByte);
+    }
+    if decoded.contains(['%', '?', '#']) {
+        return Err(PathError::ReservedCharacter);
+    }
+    let mut segments = vec![];
+    for segment in decoded.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => return Err(PathError::Traversal),
+            segment => segments.push(segment),
+        }
+    }
+    if segments.is_empty() {
+        return Err(PathError::Empty);
+    }
+    Ok(segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes() {
+        assert_eq!(sanitize_path("/a//./b/"), Ok("a/b".to_string()));
+        assert_eq!(
+            sanitize_path("/img%20one.png"),
+            Ok("img one.png".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_traversal() {
+        assert_eq!(sanitize_path("/a/../b"), Err(PathError::Traversal));
+        assert_eq!(
+            sanitize_path("/%2e%2e/etc/passwd"),
+            Err(PathError::Traversal)
+        );
+        assert_eq!(sanitize_path("/a\\..\\b"), Err(PathError::Traversal));
+    }
+
+    #[test]
+    fn rejects_double_encoding() {
+        assert_eq!(
+            sanitize_path("/%252e%252e/etc/passwd"),
+            Err(PathError::ReservedCharacter)
+        );
+    }
+
+    #[test]
+    fn rejects_query_and_fragment() {
+        assert_eq!(
+            sanitize_path("/a%3Fb%23c"),
+            Err(PathError::ReservedCharacter)
+        );
+    }
+
+    #[test]
+    fn rejects_nul_and_empty() {
+        assert_eq!(sanitize_path("/a%00b"), Err(PathError::NullByte));
+        assert_eq!(sanitize_path("/./"), Err(PathError::Empty));
+    }
+}
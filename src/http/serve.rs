@@ -0,0 +1,211 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, HeaderValue, Method, Request, Response, StatusCode};
+use bytes::Bytes;
+
+use super::cache_control::CacheControl;
+use super::range::{self, RangeError};
+use super::{etag, strip_body_for_head};
+use crate::body::Body;
+
+/// Serve static content such as assets embedded with `include_bytes!`.
+///
+/// Only `GET` and `HEAD` are allowed. The response carries a strong `ETag` and
+/// `Cache-Control: public, no-cache` so caches revalidate it, a matching `If-None-Match` gets
+/// `304 Not Modified`, and a `Range` (honoring `If-Range`) gets `206 Partial Content`, as a
+/// `multipart/byteranges` body when several ranges are requested.
+pub fn serve_bytes(req: &Request<Body>, bytes: &[u8], content_type: &str) -> Response<Body> {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        let mut res = Response::new(Body::empty());
+        *res.status_mut() = StatusCode::METHOD_NOT_ALLOWED;
+        res.headers_mut()
+            .insert(header::ALLOW, HeaderValue::from_static("GET, HEAD"));
+        return res;
+    }
+
+    let etag = etag::compute(bytes);
+    let cache_control = CacheControl::builder().public().no_cache().build();
+    let headers = req.headers();
+
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| etag::matches(value, &etag))
+    {
+        let mut res = Response::new(Body::empty());
+        *res.status_mut() = StatusCode::NOT_MODIFIED;
+        res.headers_mut().insert(header::ETAG, etag);
+        res.headers_mut()
+            .insert(header::CACHE_CONTROL, cache_control);
+        return res;
+    }
+
+    let ranges = headers
+        .get(header::RANGE)
+        .filter(|_| match headers.get(header::IF_RANGE) {
+            Some(if_range) => if_range == etag,
+            None => true,
+        })
+        .map(|value| range::parse(value, bytes.len()));
+    let ranges = match ranges {
+        Some(Ok(ranges)) => ranges,
+        Some(Err(RangeError::Unsatisfiable)) => {
+            let mut res = Response::new(Body::empty());
+            *res.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+            res.headers_mut().insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes */{}", bytes.len()))
+                    .expect("content-range is visible ASCII"),
+            );
+            return res;
+        }
+        _ => vec![],
+    };
+    let (status, body, content_range) = match ranges.as_slice() {
+        [] => (
+            StatusCode::OK,
+            Body {
+                content_type: content_type.to_string(),
+                inner: Bytes::copy_from_slice(bytes),
+            },
+            None,
+        ),
+        [content] => (
+            StatusCode::PARTIAL_CONTENT,
+            Body {
+                content_type: content_type.to_string(),
+                inner: Bytes::copy_from_slice(&bytes[content.clone()]),
+            },
+            Some(range::content_range(content, bytes.len())),
+        ),
+        ranges => {
+            // derived from the content so repeated requests get identical, cacheable responses
+            let boundary = format!(
+                "fastedge-{}",
+                etag.to_str().unwrap_or_default().trim_matches('"')
+            );
+            (
+                StatusCode::PARTIAL_CONTENT,
+                range::multipart_body(bytes, ranges, content_type, &boundary),
+                None,
+            )
+        }
+    };
+
+    let content_length = body.len();
+    let body_content_type = HeaderValue::from_str(&body.content_type);
+    let mut res = Response::new(body);
+    *res.status_mut() = status;
+    let res_headers = res.headers_mut();
+    if let Ok(value) = body_content_type {
+        res_headers.insert(header::CONTENT_TYPE, value);
+    }
+    res_headers.insert(header::ETAG, etag);
+    res_headers.insert(header::CACHE_CONTROL, cache_control);
+    res_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    res_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(content_length));
+    if let Some(content_range) = content_range {
+        res_headers.insert(header::CONTENT_RANGE, content_range);
+    }
+
+    strip_body_for_head(req, res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &[u8] = b"0123456789";
+
+    fn serve(method: Method, headers: &[(header::HeaderName, &str)]) -> Response<Body> {
+        let mut req = Request::builder().method(method);
+        for (name, value) in headers {
+            req = req.header(name, *value);
+        }
+        serve_bytes(&req.body(Body::empty()).unwrap(), CONTENT, "text/plain")
+    }
+
+    #[test]
+    fn full_content() {
+        let res = serve(Method::GET, &[]);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body().as_ref(), CONTENT);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/plain");
+        assert_eq!(res.headers()[header::ETAG], etag::compute(CONTENT));
+        assert_eq!(res.headers()[header::CACHE_CONTROL], "public, no-cache");
+        assert_eq!(res.headers()[header::CONTENT_LENGTH], "10");
+    }
+
+    #[test]
+    fn head_and_methods() {
+        let res = serve(Method::HEAD, &[]);
+        assert!(res.body().is_empty());
+        assert_eq!(res.headers()[header::CONTENT_LENGTH], "10");
+
+        let res = serve(Method::POST, &[]);
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(res.headers()[header::ALLOW], "GET, HEAD");
+    }
+
+    #[test]
+    fn not_modified() {
+        let etag = etag::compute(CONTENT);
+        let res = serve(
+            Method::GET,
+            &[(header::IF_NONE_MATCH, etag.to_str().unwrap())],
+        );
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert!(res.body().is_empty());
+    }
+
+    #[test]
+    fn single_range() {
+        let res = serve(Method::GET, &[(header::RANGE, "bytes=2-4")]);
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.body().as_ref(), b"234");
+        assert_eq!(res.headers()[header::CONTENT_RANGE], "bytes 2-4/10");
+        assert_eq!(res.headers()[header::CONTENT_LENGTH], "3");
+    }
+
+    #[test]
+    fn multiple_ranges() {
+        let res = serve(Method::GET, &[(header::RANGE, "bytes=0-1,8-")]);
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = res.headers()[header::CONTENT_TYPE].to_str().unwrap();
+        assert!(content_type.starts_with("multipart/byteranges; boundary=fastedge-"));
+        assert!(!res.headers().contains_key(header::CONTENT_RANGE));
+    }
+
+    #[test]
+    fn range_errors() {
+        let res = serve(Method::GET, &[(header::RANGE, "bytes=20-")]);
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(res.headers()[header::CONTENT_RANGE], "bytes */10");
+
+        let res = serve(Method::GET, &[(header::RANGE, "lines=1-2")]);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body().as_ref(), CONTENT);
+    }
+
+    #[test]
+    fn if_range() {
+        let res = serve(
+            Method::GET,
+            &[
+                (header::RANGE, "bytes=0-0"),
+                (header::IF_RANGE, "\"stale\""),
+            ],
+        );
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let etag = etag::compute(CONTENT);
+        let res = serve(
+            Method::GET,
+            &[
+                (header::RANGE, "bytes=0-0"),
+                (header::IF_RANGE, etag.to_str().unwrap()),
+            ],
+        );
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    }
+}
@@ -0,0 +1,206 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::time::Duration;
+
+use ::http::Request;
+use bytes::Bytes;
+
+use crate::body::Body;
+use crate::{send_request, Error};
+
+/// Event of a `text/event-stream`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Last event ID seen in the stream, including this event
+    pub id: Option<String>,
+    /// Event type, `None` for the default `message` type
+    pub event: Option<String>,
+    /// Event data, multiple `data:` lines joined with `\n`
+    pub data: String,
+}
+
+/// Iterator over the events of a `text/event-stream` body.
+///
+/// Comments are skipped and an incomplete event at the end of the stream is discarded. As in the
+/// HTML event stream decoder, invalid UTF-8 is replaced with `U+FFFD` rather than failing.
+#[derive(Debug, Clone)]
+pub struct Events {
+    body: Bytes,
+    pos: usize,
+    last_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Events {
+    /// Reconnection time requested by the last `retry:` field
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Last event ID, to send as `Last-Event-ID` when reconnecting
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_id.as_deref()
+    }
+
+    /// next line without its terminator, `None` when no complete line is left
+    fn next_line(&mut self) -> Option<Bytes> {
+        let rest = &self.body[self.pos..];
+        let end = rest.iter().position(|b| *b == b'\n' || *b == b'\r')?;
+        let line = self.body.slice(self.pos..self.pos + end);
+        self.pos += end + 1;
+        if rest[end] == b'\r' && rest.get(end + 1) == Some(&b'\n') {
+            self.pos += 1;
+        }
+        Some(line)
+    }
+}
+
+impl Iterator for Events {
+    type Item = Result<SseEvent, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut event = None;
+        let mut data: Option<String> = None;
+        loop {
+            let line = self.next_line()?;
+            let line = String::from_utf8_lossy(&line);
+            let line = line.as_ref();
+            if line.is_empty() {
+                match data.take() {
+                    Some(mut data) => {
+                        data.pop();
+                        return Some(Ok(SseEvent {
+                            id: self.last_id.clone(),
+                            event: event.take(),
+                            data,
+                        }));
+                    }
+                    None => {
+                        event = None;
+                        continue;
+                    }
+                }
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "event" => event = Some(value.to_string()),
+                "data" => {
+                    let data = data.get_or_insert_with(String::new);
+                    data.push_str(value);
+                    data.push('\n');
+                }
+                // an empty id resets the last event ID
+                "id" if !value.contains('\0') => {
+                    self.last_id = Some(value.to_string()).filter(|id| !id.is_empty())
+                }
+                // an invalid or overflowing value is ignored, keeping the previous one
+                "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                    if let Ok(millis) = value.parse() {
+                        self.retry = Some(Duration::from_millis(millis));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parse a buffered `text/event-stream` body, the content is shared rather than copied
+pub fn events(body: &Body) -> Events {
+    let body = body.inner.clone();
+    let pos = if body.starts_with("\u{feff}".as_bytes()) {
+        3
+    } else {
+        0
+    };
+    Events {
+        body,
+        pos,
+        last_id: None,
+        retry: None,
+    }
+}
+
+/// Send a request to an event stream backend and iterate over the events it returned.
+///
+/// The outbound HTTP interface returns complete responses, so this suits streams the backend
+/// ends, e.g. a batch of events since `Last-Event-ID`. A non-success status fails with
+/// [`Error::InvalidStatusCode`].
+pub fn send_request_sse(req: Request<Body>) -> Result<Events, Error> {
+    let res = send_request(req)?;
+    if !res.status().is_success() {
+        return Err(Error::InvalidStatusCode(res.status().as_u16()));
+    }
+    Ok(events(res.body()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(stream: &'static [u8]) -> Events {
+        events(&Body::from(stream))
+    }
+
+    fn event(id: Option<&str>, event: Option<&str>, data: &str) -> SseEvent {
+        SseEvent {
+            id: id.map(str::to_string),
+            event: event.map(str::to_string),
+            data: data.to_string(),
+        }
+    }
+
+    #[test]
+    fn fields() {
+        let mut events =
+            parse(b"\xef\xbb\xbf: comment\ndata: a\ndata:b\n\nevent: ping\nid: 1\ndata\r\n\r\n");
+        assert_eq!(events.next().unwrap().unwrap(), event(None, None, "a\nb"));
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            event(Some("1"), Some("ping"), "")
+        );
+        assert!(events.next().is_none());
+        assert_eq!(events.last_event_id(), Some("1"));
+    }
+
+    #[test]
+    fn incomplete_event_discarded() {
+        let mut events = parse(b"event: x\n\ndata: a\n\ndata: b\n");
+        assert_eq!(events.next().unwrap().unwrap(), event(None, None, "a"));
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn invalid_utf8_keeps_event() {
+        let mut events = parse(b"event: x\ndata: \xff\ndata: b\n\n");
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            event(None, Some("x"), "\u{fffd}\nb")
+        );
+    }
+
+    #[test]
+    fn retry() {
+        let mut events =
+            parse(b"retry: 1500\ndata: a\n\nretry: x\nretry: 99999999999999999999999\ndata: b\n\n");
+        events.next();
+        assert_eq!(events.retry(), Some(Duration::from_millis(1500)));
+        events.next();
+        assert_eq!(events.retry(), Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn empty_id_resets() {
+        let mut events = parse(b"id: 7\ndata: a\n\nid\ndata: b\n\n");
+        assert_eq!(events.next().unwrap().unwrap().id.as_deref(), Some("7"));
+        assert_eq!(events.next().unwrap().unwrap().id, None);
+    }
+}
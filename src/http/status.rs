@@ -0,0 +1,49 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+pub use ::http::status::*;
+
+/// Redirect status codes carrying a `Location` to follow
+pub const REDIRECT_CODES: [StatusCode; 5] = [
+    StatusCode::MOVED_PERMANENTLY,
+    StatusCode::FOUND,
+    StatusCode::SEE_OTHER,
+    StatusCode::TEMPORARY_REDIRECT,
+    StatusCode::PERMANENT_REDIRECT,
+];
+
+/// `2xx`
+pub fn is_success(status: StatusCode) -> bool {
+    status.is_success()
+}
+
+/// `3xx`, see [`REDIRECT_CODES`] for the codes that should be followed
+pub fn is_redirect(status: StatusCode) -> bool {
+    status.is_redirection()
+}
+
+/// `4xx`
+pub fn is_client_error(status: StatusCode) -> bool {
+    status.is_client_error()
+}
+
+/// `5xx`
+pub fn is_server_error(status: StatusCode) -> bool {
+    status.is_server_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn families() {
+        assert!(is_success(StatusCode::NO_CONTENT));
+        assert!(is_redirect(StatusCode::NOT_MODIFIED));
+        assert!(!REDIRECT_CODES.contains(&StatusCode::NOT_MODIFIED));
+        assert!(REDIRECT_CODES.iter().all(|status| is_redirect(*status)));
+        assert!(is_client_error(StatusCode::NOT_FOUND));
+        assert!(is_server_error(StatusCode::BAD_GATEWAY));
+        assert!(!is_success(StatusCode::CONTINUE));
+    }
+}
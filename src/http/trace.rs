@@ -0,0 +1,272 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::fmt::{self, Write};
+use std::time::{Duration, Instant};
+
+use ::http::{HeaderName, HeaderValue, Request};
+
+use crate::body::Body;
+use crate::encoding::hex;
+
+/// W3C `traceparent` header name
+pub const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+
+/// Timing guard measuring the time from its creation until it is dropped.
+///
+/// The duration is emitted as a `tracing` event when the span is dropped. Durations are measured
+/// with [`Instant`], which saturates to zero instead of going negative if the host clock is not
+/// monotonic.
+#[derive(Debug)]
+pub struct Span {
+    name: String,
+    start: Instant,
+}
+
+impl Span {
+    /// Start measuring a named span
+    pub fn start(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Span name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Time elapsed since the span was started
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().saturating_duration_since(self.start)
+    }
+
+    /// Finish the span and return its duration
+    pub fn finish(self) -> Duration {
+        self.elapsed()
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let elapsed = self.elapsed();
+        tracing::info!(
+            span = %self.name,
+            duration_ms = elapsed.as_secs_f64() * 1000.0,
+            "span finished"
+        );
+    }
+}
+
+/// Emit request and response body sizes of a handled request as a `tracing` event
+pub fn record_sizes(request_bytes: usize, response_bytes: usize) {
+    tracing::info!(request_bytes, response_bytes, "body sizes");
+}
+
+/// `Server-Timing` header builder
+#[derive(Debug, Clone, Default)]
+pub struct ServerTiming {
+    metrics: Vec<(String, Duration)>,
+}
+
+impl ServerTiming {
+    /// Create an empty `Server-Timing` builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a metric, characters not allowed in a metric name are replaced with `_`
+    pub fn add(&mut self, name: &str, duration: Duration) -> &mut Self {
+        let name = name
+            .chars()
+            .map(|c| if is_token(c) { c } else { '_' })
+            .collect();
+        self.metrics.push((name, duration));
+        self
+    }
+
+    /// Add the elapsed time of a span as a metric
+    pub fn add_span(&mut self, span: &Span) -> &mut Self {
+        self.add(span.name(), span.elapsed())
+    }
+
+    /// Build the `Server-Timing` header value
+    pub fn to_header_value(&self) -> HeaderValue {
+        let mut value = String::new();
+        for (name, duration) in &self.metrics {
+            if !value.is_empty() {
+                value.push_str(", ");
+            }
+            let _ = write!(value, "{};dur={:.1}", name, duration.as_secs_f64() * 1000.0);
+        }
+        HeaderValue::from_str(&value).expect("server-timing is visible ASCII")
+    }
+}
+
+fn is_token(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+/// W3C Trace Context carried in the `traceparent` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    /// Trace ID shared by all spans of a trace
+    pub trace_id: [u8; 16],
+    /// ID of the parent span
+    pub parent_id: [u8; 8],
+    /// Trace flags, bit 0 is `sampled`
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// Start a new sampled trace
+    pub fn new_root() -> Self {
+        let mut trace_id = [0; 16];
+        crate::random::fill(&mut trace_id);
+        Self {
+            trace_id,
+            parent_id: new_span_id(),
+            flags: 0x01,
+        }
+    }
+
+    /// Context for a child span, keeping the trace ID with a new span ID
+    pub fn child(&self) -> Self {
+        Self {
+            parent_id: new_span_id(),
+            ..*self
+        }
+    }
+
+    /// Whether the trace is sampled
+    pub fn is_sampled(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+
+    /// Parse a `traceparent` header value
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut fields = value.trim().split('-');
+        let version = fields.next()?;
+        let trace_id = fields.next()?;
+        let parent_id = fields.next()?;
+        let flags = fields.next()?;
+        // version 00 has exactly four fields, later versions may append more
+        if version.len() != 2 || version == "ff" || (version == "00" && fields.next().is_some()) {
+            return None;
+        }
+        let _ = decode_hex::<1>(version)?;
+
+        let context = Self {
+            trace_id: decode_hex(trace_id)?,
+            parent_id: decode_hex(parent_id)?,
+            flags: decode_hex::<1>(flags)?[0],
+        };
+        if context.trace_id == [0; 16] || context.parent_id == [0; 8] {
+            return None;
+        }
+        Some(context)
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{}-{}-{}",
+            hex::encode(&self.trace_id),
+            hex::encode(&self.parent_id),
+            hex::encode(&[self.flags])
+        )
+    }
+}
+
+/// Trace context of the incoming request, `None` if absent or invalid
+pub fn current_context(req: &Request<Body>) -> Option<TraceContext> {
+    TraceContext::parse(req.headers().get(TRACEPARENT)?.to_str().ok()?)
+}
+
+/// Set `traceparent` on an outbound request to a child span of `context`
+pub fn inject(req: &mut Request<Body>, context: &TraceContext) {
+    let value =
+        HeaderValue::from_str(&context.child().to_string()).expect("traceparent is visible ASCII");
+    req.headers_mut().insert(TRACEPARENT, value);
+}
+
+fn new_span_id() -> [u8; 8] {
+    let mut span_id = [0; 8];
+    while span_id == [0; 8] {
+        crate::random::fill(&mut span_id);
+    }
+    span_id
+}
+
+/// Decode exactly `N` bytes of lowercase hex
+fn decode_hex<const N: usize>(value: &str) -> Option<[u8; N]> {
+    if value.bytes().any(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+    hex::decode(value).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUE: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn parse_round_trip() {
+        let context = TraceContext::parse(VALUE).unwrap();
+        assert_eq!(context.trace_id[0], 0x4b);
+        assert_eq!(context.parent_id[7], 0xb7);
+        assert!(context.is_sampled());
+        assert_eq!(context.to_string(), VALUE);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for value in [
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        ] {
+            assert_eq!(TraceContext::parse(value), None, "{}", value);
+        }
+        // later versions may carry more fields
+        assert!(TraceContext::parse(&format!("01-{}-extra", &VALUE[3..])).is_some());
+    }
+
+    #[test]
+    fn child_keeps_trace_id() {
+        let context = TraceContext::parse(VALUE).unwrap();
+        let child = context.child();
+        assert_eq!(child.trace_id, context.trace_id);
+        assert_ne!(child.parent_id, [0; 8]);
+        assert_eq!(child.flags, context.flags);
+    }
+
+    #[test]
+    fn inject_header() {
+        let mut req = Request::new(Body::empty());
+        inject(&mut req, &TraceContext::parse(VALUE).unwrap());
+        let context = current_context(&req).unwrap();
+        assert_eq!(
+            context.trace_id,
+            TraceContext::parse(VALUE).unwrap().trace_id
+        );
+    }
+
+    #[test]
+    fn server_timing() {
+        let value = ServerTiming::new()
+            .add("db query", Duration::from_micros(1500))
+            .add("cache", Duration::ZERO)
+            .to_header_value();
+        assert_eq!(value, "db_query;dur=1.5, cache;dur=0.0");
+    }
+}
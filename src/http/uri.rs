@@ -0,0 +1,91 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+pub use ::http::uri::*;
+
+use std::fmt::{self, Write};
+
+/// Builder of percent-encoded query strings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryBuilder {
+    pairs: Vec<(String, String)>,
+}
+
+impl QueryBuilder {
+    /// Empty query
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a key/value pair, repeated keys are kept in order
+    pub fn append(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.pairs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Whether no pairs were added
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Append the query to the URI, after its existing query if any
+    pub fn append_to(&self, uri: &Uri) -> Result<Uri, ::http::Error> {
+        if self.is_empty() {
+            return Ok(uri.clone());
+        }
+        let path_and_query = match uri.query() {
+            Some(query) if !query.is_empty() => format!("{}?{}&{}", uri.path(), query, self),
+            _ => format!("{}?{}", uri.path(), self),
+        };
+        let mut parts = uri.clone().into_parts();
+        parts.path_and_query = Some(PathAndQuery::try_from(path_and_query)?);
+        Ok(Uri::from_parts(parts)?)
+    }
+}
+
+impl fmt::Display for QueryBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.pairs.iter().enumerate() {
+            if i > 0 {
+                f.write_char('&')?;
+            }
+            encode(f, key)?;
+            f.write_char('=')?;
+            encode(f, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// percent-encode everything except RFC 3986 unreserved characters
+fn encode(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    f.write_str(&crate::encoding::url::encode_query_component(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_string() {
+        let query = QueryBuilder::new()
+            .append("q", "a b&c")
+            .append("tag", "x")
+            .append("tag", "é/~");
+        assert_eq!(query.to_string(), "q=a%20b%26c&tag=x&tag=%C3%A9%2F~");
+        assert!(QueryBuilder::new().is_empty());
+    }
+
+    #[test]
+    fn append_to_uri() {
+        let query = QueryBuilder::new().append("page", "2");
+        let uri = Uri::from_static("https://example.com/list");
+        assert_eq!(
+            query.append_to(&uri).unwrap(),
+            "https://example.com/list?page=2"
+        );
+        let uri = Uri::from_static("/list?sort=asc");
+        assert_eq!(query.append_to(&uri).unwrap(), "/list?sort=asc&page=2");
+        assert_eq!(QueryBuilder::new().append_to(&uri).unwrap(), uri);
+    }
+}
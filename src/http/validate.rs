@@ -0,0 +1,336 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::fmt::Display;
+
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{
+    DeserializeOwned, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+use serde_json::{Map, Value};
+
+/// Error type returned by [`require_fields`] and [`validate_into`]
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// Required fields are absent or `null`
+    #[error("missing fields: {}", .0.join(", "))]
+    MissingFields(Vec<String>),
+    /// Required field is absent, with its dotted path such as `device.status`
+    #[error("missing field: {0}")]
+    MissingField(String),
+    /// Value does not match the expected structure
+    #[error("invalid value: {0}")]
+    Invalid(String),
+}
+
+/// Check all `fields` are present and not `null`.
+///
+/// Nested fields are addressed with dots, e.g. `device.status`.
+pub fn require_fields(value: &Value, fields: &[&str]) -> Result<(), ValidationError> {
+    let missing = fields
+        .iter()
+        .filter(|field| {
+            value
+                .pointer(&json_pointer(field))
+                .filter(|v| !v.is_null())
+                .is_none()
+        })
+        .map(|field| field.to_string())
+        .collect::<Vec<String>>();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError::MissingFields(missing))
+    }
+}
+
+/// Deserialize the value into `T`, reporting a missing field as
+/// [`ValidationError::MissingField`] and any other mismatch as [`ValidationError::Invalid`].
+///
+/// Deserialization stops at the first error, so only one missing field is reported. Use
+/// [`require_fields`] to list all of them.
+pub fn validate_into<T: DeserializeOwned>(value: Value) -> Result<T, ValidationError> {
+    T::deserialize(ValueDeserializer(value))
+}
+
+/// JSON pointer to a dotted field path, with `~` and `/` escaped as in RFC 6901
+fn json_pointer(field: &str) -> String {
+    field
+        .split('.')
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+impl serde::de::Error for ValidationError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ValidationError::Invalid(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        ValidationError::MissingField(field.to_string())
+    }
+}
+
+impl ValidationError {
+    /// Prefix the path of a missing field with the member it was found in
+    fn within(self, segment: &str) -> Self {
+        match self {
+            ValidationError::MissingField(path) => {
+                ValidationError::MissingField(format!("{}.{}", segment, path))
+            }
+            error => error,
+        }
+    }
+}
+
+/// `Value` deserializer reporting errors as [`ValidationError`], so missing fields are told
+/// apart without inspecting error messages
+struct ValueDeserializer(Value);
+
+impl<'de> IntoDeserializer<'de, ValidationError> for ValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Object members, errors of a member value are reported within its key
+struct ObjectAccess {
+    members: serde_json::map::IntoIter,
+    value: Option<(String, Value)>,
+}
+
+impl ObjectAccess {
+    fn new(map: Map<String, Value>) -> Self {
+        Self {
+            members: map.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for ObjectAccess {
+    type Error = ValidationError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, value)) = self.members.next() else {
+            return Ok(None);
+        };
+        let key_value = seed.deserialize(key.as_str().into_deserializer())?;
+        self.value = Some((key, value));
+        Ok(Some(key_value))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| ValidationError::Invalid("value without a key".to_string()))?;
+        seed.deserialize(ValueDeserializer(value))
+            .map_err(|error| error.within(&key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.members.len())
+    }
+}
+
+/// Array items, errors of an item are reported within its index
+struct ArrayAccess {
+    items: std::iter::Enumerate<std::vec::IntoIter<Value>>,
+}
+
+impl<'de> SeqAccess<'de> for ArrayAccess {
+    type Error = ValidationError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        let Some((index, item)) = self.items.next() else {
+            return Ok(None);
+        };
+        seed.deserialize(ValueDeserializer(item))
+            .map(Some)
+            .map_err(|error| error.within(&index.to_string()))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = ValidationError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Number(n) => {
+                if let Some(n) = n.as_u64() {
+                    visitor.visit_u64(n)
+                } else if let Some(n) = n.as_i64() {
+                    visitor.visit_i64(n)
+                } else {
+                    visitor.visit_f64(n.as_f64().unwrap_or(f64::NAN))
+                }
+            }
+            Value::String(s) => visitor.visit_string(s),
+            Value::Array(items) => visitor.visit_seq(ArrayAccess {
+                items: items.into_iter().enumerate(),
+            }),
+            Value::Object(map) => visitor.visit_map(ObjectAccess::new(map)),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::String(s) => visitor.visit_enum(s.into_deserializer()),
+            Value::Object(map) => {
+                visitor.visit_enum(MapAccessDeserializer::new(ObjectAccess::new(map)))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Device {
+        id: u32,
+        name: Option<String>,
+        status: Status,
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Fleet {
+        devices: Vec<Device>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Status {
+        Online,
+        Error { code: i32 },
+    }
+
+    #[test]
+    fn require_present_fields() {
+        let value = json!({"device": {"status": "online", "a/b": 1, "m~n": 2}, "id": null});
+        assert_eq!(
+            require_fields(&value, &["device.status", "device.a/b", "device.m~n"]),
+            Ok(())
+        );
+        assert_eq!(
+            require_fields(&value, &["id", "device.name", "device.status"]),
+            Err(ValidationError::MissingFields(vec![
+                "id".to_string(),
+                "device.name".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn validate_valid() {
+        let value = json!({"id": 7, "status": {"error": {"code": -1}}, "tags": ["a"]});
+        assert_eq!(
+            validate_into::<Device>(value),
+            Ok(Device {
+                id: 7,
+                name: None,
+                status: Status::Error { code: -1 },
+                tags: vec!["a".to_string()],
+            })
+        );
+        let value = json!({"id": 7, "name": "x", "status": "online", "tags": []});
+        assert_eq!(
+            validate_into::<Device>(value).map(|device| device.status),
+            Ok(Status::Online)
+        );
+    }
+
+    #[test]
+    fn validate_missing_field() {
+        let value = json!({"id": 7, "status": "online"});
+        assert_eq!(
+            validate_into::<Device>(value),
+            Err(ValidationError::MissingField("tags".to_string()))
+        );
+    }
+
+    #[test]
+    fn validate_missing_nested_field() {
+        let value = json!({"id": 7, "status": {"error": {}}, "tags": []});
+        assert_eq!(
+            validate_into::<Device>(value),
+            Err(ValidationError::MissingField(
+                "status.error.code".to_string()
+            ))
+        );
+        let device = json!({"id": 7, "status": "online", "tags": []});
+        let value = json!({"devices": [device, {"id": 8, "tags": []}]});
+        assert_eq!(
+            validate_into::<Fleet>(value),
+            Err(ValidationError::MissingField(
+                "devices.1.status".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_invalid() {
+        let value = json!({"id": "seven", "status": "online", "tags": []});
+        assert!(matches!(
+            validate_into::<Device>(value),
+            Err(ValidationError::Invalid(_))
+        ));
+        let value = json!({"id": 7, "status": "offline", "tags": []});
+        assert!(matches!(
+            validate_into::<Device>(value),
+            Err(ValidationError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use ::http::{header, HeaderMap, HeaderName, HeaderValue, Response};
+
+use crate::body::Body;
+
+/// Add a request header name to the response `Vary` header.
+///
+/// Names already listed are not repeated, compared case-insensitively, and nothing is added when
+/// `Vary: *` is present. Strings that aren't valid header names are ignored.
+pub fn add(res: &mut Response<Body>, header: &str) {
+    add_to(res.headers_mut(), header);
+}
+
+/// [`add`] on a header map
+pub(crate) fn add_to(headers: &mut HeaderMap, name: &str) {
+    let Ok(name) = HeaderName::from_bytes(name.trim().as_bytes()) else {
+        return;
+    };
+    let present = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| value.trim().eq_ignore_ascii_case(name.as_str()) || value.trim() == "*");
+    if !present {
+        headers.append(header::VARY, HeaderValue::from(name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_names() {
+        let mut res = Response::new(Body::empty());
+        add(&mut res, "Accept-Encoding");
+        add(&mut res, "accept-encoding");
+        add(&mut res, " origin ");
+        add(&mut res, "not a header");
+        assert_eq!(
+            res.headers()
+                .get_all(header::VARY)
+                .iter()
+                .collect::<Vec<_>>(),
+            ["accept-encoding", "origin"]
+        );
+    }
+
+    #[test]
+    fn existing_values() {
+        let mut res = Response::new(Body::empty());
+        res.headers_mut()
+            .insert(header::VARY, HeaderValue::from_static("Accept, Origin"));
+        add(&mut res, "origin");
+        assert_eq!(res.headers().get_all(header::VARY).iter().count(), 1);
+
+        res.headers_mut()
+            .insert(header::VARY, HeaderValue::from_static("*"));
+        add(&mut res, "accept-language");
+        assert_eq!(res.headers()[header::VARY], "*");
+    }
+}
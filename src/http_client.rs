@@ -1,22 +1,264 @@
 /*
 * Copyright 2024 G-Core Innovations SARL
 */
+use std::time::{Duration, Instant, SystemTime};
+
 use http::request::Parts;
+use http::{header, HeaderValue, Method as HttpMethod, StatusCode, Uri};
 
-use crate::gcore::fastedge::{http::Method, http_client};
+#[cfg(test)]
+use self::mock::http_client;
 use crate::body::Body;
+use crate::gcore::fastedge::http::Method;
+#[cfg(not(test))]
+use crate::gcore::fastedge::http_client;
+use crate::http::idempotency::IDEMPOTENCY_KEY;
+use crate::http::status::REDIRECT_CODES;
+use crate::time::Deadline;
 use crate::Error;
 
+/// Options applied by [`send_request_with_options`]
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    /// Add a `Date` header with the current time if the request has none
+    pub date: bool,
+    /// `Idempotency-Key` header value, to let the backend deduplicate retried requests
+    pub idempotency_key: Option<String>,
+    /// `If-None-Match` header value, to revalidate a cached response by its `ETag`
+    pub if_none_match: Option<String>,
+    /// `If-Modified-Since` header time, to revalidate a cached response by its `Last-Modified`.
+    ///
+    /// Not sent for times without an HTTP-date, see
+    /// [`format_http_date`](crate::time::format_http_date).
+    pub if_modified_since: Option<SystemTime>,
+    /// Maximum response body size, larger responses fail with [`Error::ResponseTooLarge`]. With
+    /// `accept_compression` the limit applies to both the encoded and the decoded body.
+    pub max_response_bytes: Option<usize>,
+    /// Names of headers removed from the request before sending
+    pub strip_headers: Vec<String>,
+    /// When set, only headers with these names are sent; headers added by other options are kept
+    pub only_headers: Option<Vec<String>>,
+    /// Attach a [`ResponseTiming`] extension to the response
+    pub timing: bool,
+    /// Total time budget shared by all calls made with these options, once it has passed calls
+    /// fail with [`Error::Timeout`] without reaching the backend.
+    ///
+    /// The host has no per-call timeout, so a call already in flight is not cut short.
+    pub deadline: Option<Deadline>,
+    /// Check the response body against `Content-MD5`, `Digest` and `Content-Digest` headers,
+    /// failing with [`Error::DigestMismatch`]; unknown algorithms are ignored
+    #[cfg(feature = "crypto")]
+    pub verify_digest: bool,
+    /// Send `Accept-Encoding: gzip, deflate` (with `br` first under the `brotli` feature) if the
+    /// request has none and decode compressed responses, see [`compression::decompress`](crate::http::compression::decompress)
+    #[cfg(feature = "compression")]
+    pub accept_compression: bool,
+}
+
+/// Backend call timing, attached as a response extension when [`RequestOptions::timing`] is set.
+///
+/// The host does not report DNS, connect or first byte times, so only the total is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseTiming {
+    /// Time from handing the request to the host until the complete response was returned
+    pub total: Duration,
+}
+
 /// implementation of http_client
 pub fn send_request(req: ::http::Request<Body>) -> Result<::http::Response<Body>, Error> {
+    send_request_with_options(req, &RequestOptions::default())
+}
+
+/// Send request with additional [`RequestOptions`]
+pub fn send_request_with_options(
+    req: ::http::Request<Body>,
+    options: &RequestOptions,
+) -> Result<::http::Response<Body>, Error> {
+    if options
+        .deadline
+        .is_some_and(|deadline| deadline.is_expired())
+    {
+        return Err(Error::Timeout);
+    }
+
     // convert http::Request<Body> to http_client::Response
-    let (parts, body) = req.into_parts();
-    let request = (&parts, &body).try_into()?;
+    let (mut parts, body) = req.into_parts();
+    filter_headers(&mut parts, options);
+    if options.date && !parts.headers.contains_key(header::DATE) {
+        let date = crate::time::format_http_date(crate::time::now());
+        if let Some(date) = date.and_then(|date| HeaderValue::from_str(&date).ok()) {
+            parts.headers.insert(header::DATE, date);
+        }
+    }
+    if let Some(key) = &options.idempotency_key {
+        let key = HeaderValue::from_str(key).map_err(::http::Error::from)?;
+        parts.headers.insert(IDEMPOTENCY_KEY, key);
+    }
+    if let Some(etag) = &options.if_none_match {
+        let etag = HeaderValue::from_str(etag).map_err(::http::Error::from)?;
+        parts.headers.insert(header::IF_NONE_MATCH, etag);
+    }
+    if let Some(time) = options.if_modified_since {
+        let date = crate::time::format_http_date(time);
+        if let Some(date) = date.and_then(|date| HeaderValue::from_str(&date).ok()) {
+            parts.headers.insert(header::IF_MODIFIED_SINCE, date);
+        }
+    }
+    #[cfg(feature = "compression")]
+    if options.accept_compression && !parts.headers.contains_key(header::ACCEPT_ENCODING) {
+        let accept_encoding = if cfg!(feature = "brotli") {
+            "br, gzip, deflate"
+        } else {
+            "gzip, deflate"
+        };
+        parts.headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_static(accept_encoding),
+        );
+    }
+    set_host_header(&mut parts);
+    let uri = parts.uri.to_string();
+    let headers = crate::http::headers::to_bindgen(&parts.headers);
+    let headers = headers
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect::<Vec<(&str, &str)>>();
+    let request = http_client::Request {
+        method: to_http_client_method(&parts.method)?,
+        uri: &uri,
+        headers: &headers,
+        // body bytes are passed to the host without an intermediate copy, an empty body is not
+        // sent at all so bodyless requests carry no zero-length body
+        body: if body.is_empty() { None } else { Some(&body) },
+    };
 
     // call http-backend component send_request
-    let response = http_client::send_request(&request).map_err(Error::BindgenHttpError)?;
+    let start = Instant::now();
+    let response = http_client::send_request(request).map_err(Error::BindgenHttpError)?;
+    let total = start.elapsed();
+
+    if let (Some(limit), Some(body)) = (options.max_response_bytes, &response.body) {
+        if body.len() > limit {
+            return Err(Error::ResponseTooLarge(limit));
+        }
+    }
+
+    let mut response = translate_http_client_to_response(response)?;
+    #[cfg(feature = "crypto")]
+    // a 304 carries the digest of the cached representation, not of its empty body
+    if options.verify_digest
+        && response.status() != StatusCode::NOT_MODIFIED
+        && !digest_matches(&response)
+    {
+        return Err(Error::DigestMismatch);
+    }
+    #[cfg(feature = "compression")]
+    if options.accept_compression {
+        // the limit applies to the decoded body as well, so a compression bomb can't get past it
+        response = match options.max_response_bytes {
+            Some(limit) => crate::http::compression::decompress_limited(response, limit)?,
+            None => crate::http::compression::decompress(response)?,
+        };
+    }
+    if options.timing {
+        response.extensions_mut().insert(ResponseTiming { total });
+    }
+    Ok(response)
+}
+
+/// Send request following up to `max_redirects` redirects.
+///
+/// `303 See Other`, and `301`/`302` after a `POST`, are followed with a bodyless `GET`; other
+/// redirects repeat the original method and body. `Authorization` and `Cookie` headers are dropped
+/// when the redirect changes the origin, i.e. the scheme, host or port. A redirect back to an
+/// already visited URL fails with [`Error::RedirectLoop`], exceeding the limit with
+/// [`Error::TooManyRedirects`].
+pub fn send_request_follow_redirects(
+    req: ::http::Request<Body>,
+    options: &RequestOptions,
+    max_redirects: usize,
+) -> Result<::http::Response<Body>, Error> {
+    let (mut parts, mut body) = req.into_parts();
+    let mut visited = vec![parts.uri.to_string()];
+    loop {
+        let mut next = ::http::Request::new(body.clone());
+        *next.method_mut() = parts.method.clone();
+        *next.uri_mut() = parts.uri.clone();
+        *next.headers_mut() = parts.headers.clone();
+        let res = send_request_with_options(next, options)?;
+
+        let status = res.status();
+        if !REDIRECT_CODES.contains(&status) {
+            return Ok(res);
+        }
+        let Some(location) = res
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| resolve_location(&parts.uri, location))
+        else {
+            return Ok(res);
+        };
+
+        let url = location.to_string();
+        if visited.contains(&url) {
+            return Err(Error::RedirectLoop(url));
+        }
+        if visited.len() > max_redirects {
+            return Err(Error::TooManyRedirects(max_redirects));
+        }
+        visited.push(url);
+
+        let to_get = status == StatusCode::SEE_OTHER
+            || (parts.method == HttpMethod::POST
+                && matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND));
+        if to_get && parts.method != HttpMethod::HEAD {
+            parts.method = HttpMethod::GET;
+            body = Body::empty();
+            parts.headers.remove(header::CONTENT_TYPE);
+            parts.headers.remove(header::CONTENT_LENGTH);
+        }
+        if origin(&location) != origin(&parts.uri) {
+            parts.headers.remove(header::AUTHORIZATION);
+            parts.headers.remove(header::COOKIE);
+        }
+        // Host is derived from the new URI on the next hop
+        parts.headers.remove(header::HOST);
+        parts.uri = location;
+    }
+}
+
+/// scheme, lowercase host and effective port of a URI
+fn origin(uri: &Uri) -> (Option<&str>, Option<String>, Option<u16>) {
+    let port = uri.port_u16().or(match uri.scheme_str() {
+        Some("http") => Some(80),
+        Some("https") => Some(443),
+        _ => None,
+    });
+    let host = uri.host().map(str::to_ascii_lowercase);
+    (uri.scheme_str(), host, port)
+}
 
-    translate_http_client_to_response(response)
+/// resolve a `Location` header value against the request URI
+fn resolve_location(base: &Uri, location: &str) -> Option<Uri> {
+    let location = location.trim();
+    if let Ok(uri) = location.parse::<Uri>() {
+        if uri.scheme().is_some() {
+            return Some(uri);
+        }
+    }
+    let scheme = base.scheme_str()?;
+    if let Some(rest) = location.strip_prefix("//") {
+        return format!("{}://{}", scheme, rest).parse().ok();
+    }
+    let authority = base.authority()?;
+    let path = if location.starts_with('/') {
+        location.to_string()
+    } else {
+        let dir = base.path().rsplit_once('/').map_or("", |(dir, _)| dir);
+        format!("{}/{}", dir, location)
+    };
+    format!("{}://{}{}", scheme, authority, path).parse().ok()
 }
 
 /// translate http::Response<Body> from http_client::Response
@@ -24,6 +266,7 @@ fn translate_http_client_to_response(
     res: http_client::Response,
 ) -> Result<::http::Response<Body>, Error> {
     let builder = http::Response::builder().status(res.status);
+    // builder appends headers, so repeated headers like `Set-Cookie` are all preserved
     let builder = if let Some(headers) = res.headers {
         headers
             .iter()
@@ -37,29 +280,74 @@ fn translate_http_client_to_response(
     Ok(response)
 }
 
-impl TryFrom<(&Parts, &Body)> for http_client::Request {
-    type Error = Error;
+/// check every digest header with a supported algorithm against the body
+#[cfg(feature = "crypto")]
+fn digest_matches(res: &::http::Response<Body>) -> bool {
+    use crate::crypto::{md5, sha256, to_base64};
 
-    fn try_from((parts, body): (&Parts, &Body)) -> Result<Self, Self::Error> {
-        let method = to_http_client_method(&parts.method)?;
+    let body = res.body();
+    let mut expected = vec![];
+    for value in res.headers().get_all("content-md5") {
+        expected.push(("md5", value.to_str().unwrap_or_default().to_string()));
+    }
+    // RFC 3230 `Digest: SHA-256=<base64>` and RFC 9530 `Content-Digest: sha-256=:<base64>:`
+    for name in ["digest", "content-digest"] {
+        for value in res.headers().get_all(name) {
+            let Ok(value) = value.to_str() else {
+                return false;
+            };
+            for entry in value.split(',') {
+                let Some((algorithm, digest)) = entry.split_once('=') else {
+                    continue;
+                };
+                let algorithm = match algorithm.trim().to_ascii_lowercase().as_str() {
+                    "sha-256" => "sha-256",
+                    "md5" => "md5",
+                    _ => continue,
+                };
+                expected.push((algorithm, digest.trim().trim_matches(':').to_string()));
+            }
+        }
+    }
 
-        let headers = parts
-            .headers
-            .iter()
-            .map(|(name, value)| {
-                (
-                    name.to_string(),
-                    value.to_str().map(|s| s.to_string()).unwrap(),
-                )
-            })
-            .collect::<Vec<(String, String)>>();
+    expected.iter().all(|(algorithm, digest)| {
+        let actual = match *algorithm {
+            "sha-256" => to_base64(&sha256(body)),
+            _ => to_base64(&md5(body)),
+        };
+        actual.trim_end_matches('=') == digest.trim().trim_end_matches('=')
+    })
+}
 
-        Ok(http_client::Request {
-            method,
-            uri: parts.uri.to_string(),
-            headers,
-            body: Some(body.to_vec()),
-        })
+/// apply header allowlist and denylist, names are compared case-insensitively
+fn filter_headers(parts: &mut Parts, options: &RequestOptions) {
+    for name in &options.strip_headers {
+        parts.headers.remove(name.as_str());
+    }
+    if let Some(only) = &options.only_headers {
+        let names = parts.headers.keys().cloned().collect::<Vec<_>>();
+        for name in names {
+            if !only
+                .iter()
+                .any(|allowed| name.as_str().eq_ignore_ascii_case(allowed))
+            {
+                parts.headers.remove(name);
+            }
+        }
+    }
+}
+
+/// derive Host from the URI authority when not set explicitly
+fn set_host_header(parts: &mut Parts) {
+    if parts.headers.contains_key(header::HOST) {
+        return;
+    }
+    let host = parts.uri.host().map(|host| match parts.uri.port_u16() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    });
+    if let Some(host) = host.and_then(|host| HeaderValue::from_str(&host).ok()) {
+        parts.headers.insert(header::HOST, host);
     }
 }
 
@@ -75,3 +363,436 @@ fn to_http_client_method(method: &::http::Method) -> Result<Method, Error> {
         method => return Err(Error::UnsupportedMethod(method.to_owned())),
     })
 }
+
+#[cfg(test)]
+mod mock {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    use crate::gcore::fastedge::http::Method;
+
+    /// request as received by the mocked host
+    #[derive(Debug, Clone)]
+    pub struct Sent {
+        pub method: Method,
+        pub uri: String,
+        pub headers: Vec<(String, String)>,
+        pub body: Option<Vec<u8>>,
+    }
+
+    thread_local! {
+        pub static RESPONSES: RefCell<VecDeque<http_client::Response>> =
+            const { RefCell::new(VecDeque::new()) };
+        pub static SENT: RefCell<Vec<Sent>> = const { RefCell::new(vec![]) };
+    }
+
+    /// queue a response for the next request
+    pub fn respond(status: u16, headers: &[(&str, &str)], body: &[u8]) {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        RESPONSES.with(|responses| {
+            responses.borrow_mut().push_back(http_client::Response {
+                status,
+                headers: Some(headers),
+                body: Some(body.to_vec()).filter(|body| !body.is_empty()),
+            })
+        });
+    }
+
+    /// requests sent so far, clearing the record
+    pub fn sent() -> Vec<Sent> {
+        SENT.with(|sent| sent.take())
+    }
+
+    pub mod http_client {
+        use crate::gcore::fastedge::http::Error;
+        pub use crate::gcore::fastedge::http_client::{Request, Response};
+
+        pub fn send_request(req: Request<'_>) -> Result<Response, Error> {
+            super::SENT.with(|sent| {
+                sent.borrow_mut().push(super::Sent {
+                    method: req.method,
+                    uri: req.uri.to_string(),
+                    headers: req
+                        .headers
+                        .iter()
+                        .map(|(name, value)| (name.to_string(), value.to_string()))
+                        .collect(),
+                    body: req.body.map(<[u8]>::to_vec),
+                })
+            });
+            super::RESPONSES
+                .with(|responses| responses.borrow_mut().pop_front())
+                .ok_or(Error::RequestError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::{respond, sent};
+    use super::*;
+
+    fn request(method: HttpMethod, uri: &str) -> ::http::Request<Body> {
+        ::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn request_parts() {
+        respond(200, &[], b"");
+        let mut req = request(HttpMethod::PUT, "http://a/items");
+        *req.body_mut() = Body::from("data");
+        send_request(req).unwrap();
+        let sent = sent().remove(0);
+        assert!(matches!(sent.method, Method::Put));
+        assert_eq!(sent.uri, "http://a/items");
+        assert_eq!(sent.body.as_deref(), Some(&b"data"[..]));
+    }
+
+    #[test]
+    fn host_and_date() {
+        let options = RequestOptions {
+            date: true,
+            ..Default::default()
+        };
+        respond(200, &[], b"");
+        send_request_with_options(
+            request(HttpMethod::GET, "http://example.com:8080/"),
+            &options,
+        )
+        .unwrap();
+        let headers = sent().remove(0).headers;
+        assert!(headers.contains(&("host".to_string(), "example.com:8080".to_string())));
+        let date = headers.iter().find(|(name, _)| name == "date").unwrap();
+        assert!(crate::time::parse_http_date(&date.1).is_some());
+    }
+
+    #[test]
+    fn repeated_response_headers() {
+        respond(200, &[("set-cookie", "a=1"), ("set-cookie", "b=2")], b"");
+        let res = send_request(request(HttpMethod::GET, "http://a/")).unwrap();
+        assert_eq!(
+            res.headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .collect::<Vec<_>>(),
+            ["a=1", "b=2"]
+        );
+    }
+
+    #[test]
+    fn max_response_bytes() {
+        let options = RequestOptions {
+            max_response_bytes: Some(4),
+            ..Default::default()
+        };
+        respond(200, &[], b"1234");
+        let res = send_request_with_options(request(HttpMethod::GET, "http://a/"), &options);
+        assert_eq!(res.unwrap().body().len(), 4);
+        respond(200, &[], b"12345");
+        let res = send_request_with_options(request(HttpMethod::GET, "http://a/"), &options);
+        assert!(matches!(res, Err(Error::ResponseTooLarge(4))));
+    }
+
+    #[test]
+    fn resolve_locations() {
+        let base = Uri::from_static("https://example.com/a/b?q=1");
+        let resolve = |location| resolve_location(&base, location).map(|uri| uri.to_string());
+        assert_eq!(
+            resolve("http://other.com/x").as_deref(),
+            Some("http://other.com/x")
+        );
+        assert_eq!(
+            resolve("//cdn.example.com/x").as_deref(),
+            Some("https://cdn.example.com/x")
+        );
+        assert_eq!(
+            resolve("/x?y=2").as_deref(),
+            Some("https://example.com/x?y=2")
+        );
+        assert_eq!(resolve("c").as_deref(), Some("https://example.com/a/c"));
+        assert_eq!(resolve_location(&Uri::from_static("/a"), "/x"), None);
+    }
+
+    #[test]
+    fn follow_redirects() {
+        respond(302, &[("location", "/next")], b"");
+        respond(303, &[("location", "https://other.com/done")], b"");
+        respond(200, &[], b"ok");
+        let mut req = request(HttpMethod::POST, "https://example.com/start");
+        req.headers_mut()
+            .insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer x"));
+        *req.body_mut() = Body::from("form");
+
+        let res = send_request_follow_redirects(req, &RequestOptions::default(), 5).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body().as_ref(), b"ok");
+
+        let sent = sent();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[1].uri, "https://example.com/next");
+        assert!(matches!(sent[1].method, Method::Get));
+        assert_eq!(sent[1].body, None);
+        assert!(sent[1]
+            .headers
+            .iter()
+            .any(|(name, _)| name == "authorization"));
+        assert_eq!(sent[2].uri, "https://other.com/done");
+        assert!(sent[2]
+            .headers
+            .iter()
+            .all(|(name, _)| name != "authorization"));
+        assert!(sent[2]
+            .headers
+            .contains(&("host".to_string(), "other.com".to_string())));
+    }
+
+    #[test]
+    fn redirect_origin_change() {
+        for location in ["http://example.com/b", "https://example.com:8443/b"] {
+            respond(302, &[("location", location)], b"");
+            respond(200, &[], b"");
+            let mut req = request(HttpMethod::GET, "https://example.com/a");
+            req.headers_mut()
+                .insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer x"));
+            req.headers_mut()
+                .insert(header::COOKIE, HeaderValue::from_static("session=1"));
+            send_request_follow_redirects(req, &RequestOptions::default(), 5).unwrap();
+            let sent = sent();
+            assert!(sent[0].headers.iter().any(|(name, _)| name == "cookie"));
+            assert!(
+                sent[1]
+                    .headers
+                    .iter()
+                    .all(|(name, _)| name != "authorization" && name != "cookie"),
+                "{}",
+                location
+            );
+        }
+
+        respond(302, &[("location", "https://EXAMPLE.com:443/b")], b"");
+        respond(200, &[], b"");
+        let mut req = request(HttpMethod::GET, "https://example.com/a");
+        req.headers_mut()
+            .insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer x"));
+        send_request_follow_redirects(req, &RequestOptions::default(), 5).unwrap();
+        assert!(sent()[1]
+            .headers
+            .iter()
+            .any(|(name, _)| name == "authorization"));
+    }
+
+    #[test]
+    fn redirect_keeps_method() {
+        respond(307, &[("location", "/b")], b"");
+        respond(204, &[], b"");
+        let mut req = request(HttpMethod::PUT, "https://example.com/a");
+        *req.body_mut() = Body::from("data");
+        let res = send_request_follow_redirects(req, &RequestOptions::default(), 1).unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let sent = sent();
+        assert!(matches!(sent[1].method, Method::Put));
+        assert_eq!(sent[1].body.as_deref(), Some(&b"data"[..]));
+    }
+
+    #[test]
+    fn redirect_loop() {
+        respond(301, &[("location", "/b")], b"");
+        respond(301, &[("location", "https://example.com/a")], b"");
+        let req = request(HttpMethod::GET, "https://example.com/a");
+        let err = send_request_follow_redirects(req, &RequestOptions::default(), 10).unwrap_err();
+        assert!(matches!(err, Error::RedirectLoop(url) if url == "https://example.com/a"));
+        assert_eq!(sent().len(), 2);
+    }
+
+    #[test]
+    fn too_many_redirects() {
+        respond(302, &[("location", "/1")], b"");
+        respond(302, &[("location", "/2")], b"");
+        respond(302, &[("location", "/3")], b"");
+        let req = request(HttpMethod::GET, "https://example.com/0");
+        let err = send_request_follow_redirects(req, &RequestOptions::default(), 2).unwrap_err();
+        assert!(matches!(err, Error::TooManyRedirects(2)));
+        assert_eq!(sent().len(), 3);
+    }
+
+    #[test]
+    fn redirect_without_location() {
+        respond(302, &[], b"moved");
+        let req = request(HttpMethod::GET, "https://example.com/");
+        let res = send_request_follow_redirects(req, &RequestOptions::default(), 2).unwrap();
+        assert_eq!(res.status(), StatusCode::FOUND);
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn digest_verification() {
+        use crate::crypto::{md5, sha256, to_base64};
+
+        let options = RequestOptions {
+            verify_digest: true,
+            ..Default::default()
+        };
+        let sha = to_base64(&sha256(b"hello"));
+        let content_digest = format!("sha-256=:{}:", sha);
+        let digest = format!("SHA-256={}, unknown=xyz", sha);
+        let md5 = to_base64(&md5(b"hello"));
+        respond(
+            200,
+            &[
+                ("content-digest", &content_digest),
+                ("digest", &digest),
+                ("content-md5", &md5),
+            ],
+            b"hello",
+        );
+        let res = send_request_with_options(request(HttpMethod::GET, "http://a/"), &options);
+        assert_eq!(res.unwrap().body().as_ref(), b"hello");
+
+        respond(200, &[("content-digest", &content_digest)], b"hellO");
+        let res = send_request_with_options(request(HttpMethod::GET, "http://a/"), &options);
+        assert!(matches!(res, Err(Error::DigestMismatch)));
+
+        respond(304, &[("content-digest", &content_digest)], b"");
+        let res = send_request_with_options(request(HttpMethod::GET, "http://a/"), &options);
+        assert_eq!(res.unwrap().status(), StatusCode::NOT_MODIFIED);
+
+        // without the option a mismatch goes unnoticed
+        respond(200, &[("content-md5", &md5)], b"other");
+        let res = send_request(request(HttpMethod::GET, "http://a/"));
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn header_filters() {
+        let options = RequestOptions {
+            strip_headers: vec!["X-Debug".to_string()],
+            only_headers: Some(vec!["accept".to_string(), "X-Debug".to_string()]),
+            idempotency_key: Some("key-1".to_string()),
+            ..Default::default()
+        };
+        let req = ::http::Request::builder()
+            .uri("http://example.com/")
+            .header(header::ACCEPT, "*/*")
+            .header(header::COOKIE, "a=1")
+            .header("x-debug", "1")
+            .body(Body::empty())
+            .unwrap();
+        respond(200, &[], b"");
+        send_request_with_options(req, &options).unwrap();
+        let mut headers = sent().remove(0).headers;
+        headers.sort();
+        assert_eq!(
+            headers,
+            [
+                ("accept".to_string(), "*/*".to_string()),
+                ("host".to_string(), "example.com".to_string()),
+                ("idempotency-key".to_string(), "key-1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn timing() {
+        let options = RequestOptions {
+            timing: true,
+            ..Default::default()
+        };
+        respond(200, &[], b"");
+        let res = send_request_with_options(request(HttpMethod::GET, "http://a/"), &options);
+        assert!(res.unwrap().extensions().get::<ResponseTiming>().is_some());
+        respond(200, &[], b"");
+        let res = send_request(request(HttpMethod::GET, "http://a/"));
+        assert!(res.unwrap().extensions().get::<ResponseTiming>().is_none());
+    }
+
+    #[test]
+    fn empty_body_not_sent() {
+        respond(200, &[], b"");
+        respond(200, &[], b"");
+        send_request(request(HttpMethod::POST, "http://a/")).unwrap();
+        let mut req = request(HttpMethod::POST, "http://a/");
+        *req.body_mut() = Body::from("x");
+        send_request(req).unwrap();
+        let sent = sent();
+        assert_eq!(sent[0].body, None);
+        assert_eq!(sent[1].body.as_deref(), Some(&b"x"[..]));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn accept_compression() {
+        use std::io::Write;
+
+        use flate2::{write::GzEncoder, Compression};
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[b'a'; 1000]).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let options = RequestOptions {
+            accept_compression: true,
+            ..Default::default()
+        };
+        respond(200, &[("content-encoding", "gzip")], &gzipped);
+        let res = send_request_with_options(request(HttpMethod::GET, "http://a/"), &options);
+        assert_eq!(res.unwrap().body().as_ref(), &[b'a'; 1000][..]);
+        let headers = sent().remove(0).headers;
+        assert!(headers
+            .iter()
+            .any(|(name, value)| name == "accept-encoding" && value.contains("gzip")));
+
+        // the limit applies to the decoded body
+        let options = RequestOptions {
+            max_response_bytes: Some(100),
+            ..options
+        };
+        respond(200, &[("content-encoding", "gzip")], &gzipped);
+        let res = send_request_with_options(request(HttpMethod::GET, "http://a/"), &options);
+        assert!(matches!(res, Err(Error::ResponseTooLarge(100))));
+    }
+
+    #[test]
+    fn conditional_headers() {
+        let options = RequestOptions {
+            if_none_match: Some("\"v1\"".to_string()),
+            if_modified_since: Some(std::time::UNIX_EPOCH),
+            ..Default::default()
+        };
+        respond(304, &[], b"");
+        let res = send_request_with_options(request(HttpMethod::GET, "http://a/"), &options);
+        assert!(crate::http::ResponseExt::is_not_modified(&res.unwrap()));
+        let headers = sent().remove(0).headers;
+        assert!(headers.contains(&("if-none-match".to_string(), "\"v1\"".to_string())));
+        assert!(headers.contains(&(
+            "if-modified-since".to_string(),
+            "Thu, 01 Jan 1970 00:00:00 GMT".to_string()
+        )));
+
+        let options = RequestOptions {
+            if_modified_since: Some(std::time::UNIX_EPOCH - Duration::from_secs(1)),
+            ..Default::default()
+        };
+        respond(200, &[], b"");
+        send_request_with_options(request(HttpMethod::GET, "http://a/"), &options).unwrap();
+        let headers = sent().remove(0).headers;
+        assert!(!headers.iter().any(|(name, _)| name == "if-modified-since"));
+    }
+
+    #[test]
+    fn expired_deadline() {
+        let options = RequestOptions {
+            deadline: Some(Deadline::after(Duration::ZERO)),
+            ..Default::default()
+        };
+        let res = send_request_with_options(request(HttpMethod::GET, "http://a/"), &options);
+        assert!(matches!(res, Err(Error::Timeout)));
+        assert!(sent().is_empty());
+    }
+}
@@ -3,29 +3,50 @@
 */
 //! # Rust SDK for FastEdge.
 
-pub extern crate http;
-
 pub use fastedge_derive::http;
-pub use http_client::send_request;
+pub use http_client::{
+    send_request, send_request_follow_redirects, send_request_with_options, RequestOptions,
+    ResponseTiming,
+};
 
 pub use crate::exports::gcore::fastedge::http_handler;
-use crate::gcore::fastedge::http::{Error as HttpError, Method, Request, Response};
+use crate::gcore::fastedge::http::{Error as HttpError, Method, Response};
+use crate::http_handler::Request;
 
+/// Message digests
+#[cfg(feature = "crypto")]
+pub mod crypto;
+/// Panic and error reporting
+pub mod diagnostics;
+/// Binary-to-text encodings
+pub mod encoding;
+/// Environment variables configured for the app
+pub mod env;
+/// Read-only access to files mounted by the runtime
+pub mod fs;
+/// Re-export of the [`http`](https://docs.rs/http) crate extended with FastEdge helpers
+pub mod http;
 /// Implementation of Outbound HTTP component
 mod http_client;
+/// Host-backed random number generation
+pub mod random;
+/// Handler test helpers
+#[cfg(feature = "testing")]
+pub mod testing;
+/// Time helpers
+pub mod time;
 
-pub mod wasi_nn {
-    #![allow(missing_docs)]
-    wit_bindgen::generate!({
-        world: "ml",
-        path: "wasi-nn/wit"
-    });
-}
+/// WASI-NN bindings and inference helpers
+pub mod wasi_nn;
 
 wit_bindgen::generate!({
     world: "http-reactor",
     path: "wit",
-    pub_export_macro: true
+    pub_export_macro: true,
+    // outbound requests borrow headers and body instead of copying them into the bindings
+    ownership: Borrowing {
+        duplicate_if_necessary: true
+    }
 });
 
 /// Error type returned by [`send_request`]
@@ -46,16 +67,75 @@ pub enum Error {
     /// Wraps response InvalidStatusCode error
     #[error("invalid status code {0}")]
     InvalidStatusCode(u16),
+    /// Response body exceeds [`RequestOptions::max_response_bytes`]
+    #[error("response body exceeds {0} bytes")]
+    ResponseTooLarge(usize),
+    /// Redirect chain returned to an already visited URL
+    #[error("redirect loop at {0}")]
+    RedirectLoop(String),
+    /// Redirect chain is longer than the allowed number of redirects
+    #[error("more than {0} redirects")]
+    TooManyRedirects(usize),
+    /// [`RequestOptions::deadline`] passed before the backend call
+    #[error("deadline exceeded")]
+    Timeout,
+    /// Response body does not match its digest header
+    #[cfg(feature = "crypto")]
+    #[error("response body does not match its digest")]
+    DigestMismatch,
+    /// Wraps JSON (de)serialization error
+    #[cfg(feature = "json")]
+    #[error("serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+impl Error {
+    /// Suggested response status code for a handler that fails with this error
+    pub fn status_hint(&self) -> ::http::StatusCode {
+        match self {
+            Error::UnsupportedMethod(_) => ::http::StatusCode::METHOD_NOT_ALLOWED,
+            Error::BindgenHttpError(HttpError::TooManyRequests) => {
+                ::http::StatusCode::TOO_MANY_REQUESTS
+            }
+            Error::BindgenHttpError(HttpError::RequestError | HttpError::RuntimeError) => {
+                ::http::StatusCode::BAD_GATEWAY
+            }
+            Error::InvalidStatusCode(_)
+            | Error::ResponseTooLarge(_)
+            | Error::RedirectLoop(_)
+            | Error::TooManyRedirects(_) => ::http::StatusCode::BAD_GATEWAY,
+            Error::Timeout => ::http::StatusCode::GATEWAY_TIMEOUT,
+            Error::BindgenHttpError(_) | Error::HttpError(_) | Error::InvalidBody => {
+                ::http::StatusCode::INTERNAL_SERVER_ERROR
+            }
+            #[cfg(feature = "json")]
+            Error::Serialization(_) => ::http::StatusCode::INTERNAL_SERVER_ERROR,
+            #[cfg(feature = "crypto")]
+            Error::DigestMismatch => ::http::StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    /// Whether the failed operation may succeed if repeated, [`Error::Timeout`] included as a
+    /// retry with a fresh or longer deadline may complete
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Error::BindgenHttpError(
+                HttpError::RequestError | HttpError::RuntimeError | HttpError::TooManyRequests
+            ) | Error::Timeout
+        )
+    }
 }
 
 /// Helper types for http component
 pub mod body {
-    use std::ops::Deref;
+    use std::fmt::{self, Write};
+    use std::ops::{Bound, Deref, RangeBounds};
 
-    use bytes::Bytes;
+    use bytes::{Buf, Bytes, BytesMut};
 
-    /// FastEdge request/response body
-    #[derive(Debug)]
+    /// FastEdge request/response body, cloning is cheap as the bytes are shared
+    #[derive(Debug, Clone)]
     pub struct Body {
         pub(crate) content_type: String,
         pub(crate) inner: Bytes,
@@ -105,6 +185,21 @@ pub mod body {
         }
     }
 
+    impl From<Bytes> for Body {
+        fn from(value: Bytes) -> Self {
+            Body {
+                content_type: mime::APPLICATION_OCTET_STREAM.to_string(),
+                inner: value,
+            }
+        }
+    }
+
+    impl From<BytesMut> for Body {
+        fn from(value: BytesMut) -> Self {
+            Body::from(value.freeze())
+        }
+    }
+
     #[cfg(feature = "json")]
     impl TryFrom<serde_json::Value> for Body {
         type Error = serde_json::Error;
@@ -135,6 +230,126 @@ pub mod body {
         pub fn content_type(&self) -> String {
             self.content_type.to_owned()
         }
+
+        /// Text body formatted directly into the body buffer, e.g.
+        /// `Body::from_fmt(format_args!("len = {}", len))`
+        pub fn from_fmt(args: fmt::Arguments<'_>) -> Self {
+            let inner = match args.as_str() {
+                Some(text) => Bytes::from_static(text.as_bytes()),
+                None => {
+                    let mut buf = BytesMut::new();
+                    buf.write_fmt(args)
+                        .expect("formatting into a buffer does not fail");
+                    buf.freeze()
+                }
+            };
+            Body {
+                content_type: mime::TEXT_PLAIN_UTF_8.to_string(),
+                inner,
+            }
+        }
+
+        /// [`Read`](std::io::Read) over the body content, the content is shared rather than copied
+        pub fn reader(&self) -> impl std::io::Read {
+            self.inner.clone().reader()
+        }
+
+        /// Iterator over `\n` or `\r\n` terminated lines, e.g. of an NDJSON response, yielding
+        /// [`Error::InvalidBody`](crate::Error::InvalidBody) for a line that isn't UTF-8. A final
+        /// line without a terminator is included.
+        pub fn lines(&self) -> Lines {
+            Lines {
+                rest: self.inner.clone(),
+            }
+        }
+
+        /// Install a new body in place, returning the previous one
+        pub fn replace(&mut self, new: impl Into<Body>) -> Body {
+            std::mem::replace(self, new.into())
+        }
+
+        /// Replace the body content type
+        pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+            self.content_type = content_type.into();
+            self
+        }
+
+        /// Body content type parsed as MIME type, `None` if it can't be parsed
+        pub fn mime(&self) -> Option<mime::Mime> {
+            self.content_type.parse().ok()
+        }
+
+        /// Zero-copy sub-range of the body with the same content type, out of range or inverted
+        /// bounds produce an empty body
+        pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+            let start = match range.start_bound() {
+                Bound::Included(&start) => start,
+                Bound::Excluded(&start) => start.saturating_add(1),
+                Bound::Unbounded => 0,
+            };
+            let end = match range.end_bound() {
+                Bound::Included(&end) => end.saturating_add(1),
+                Bound::Excluded(&end) => end,
+                Bound::Unbounded => self.inner.len(),
+            };
+            let inner = if start <= end && end <= self.inner.len() {
+                self.inner.slice(start..end)
+            } else {
+                Bytes::new()
+            };
+            Body {
+                content_type: self.content_type.clone(),
+                inner,
+            }
+        }
+
+        /// Concatenate bodies into one, the content type is taken from the first part
+        pub fn concat(parts: impl IntoIterator<Item = Body>) -> Self {
+            let mut parts = parts.into_iter();
+            let Some(first) = parts.next() else {
+                return Body::empty();
+            };
+            let rest = parts.collect::<Vec<Body>>();
+            if rest.is_empty() {
+                return first;
+            }
+
+            let len = first.len() + rest.iter().map(|part| part.len()).sum::<usize>();
+            let mut inner = BytesMut::with_capacity(len);
+            inner.extend_from_slice(&first);
+            for part in rest {
+                inner.extend_from_slice(&part);
+            }
+            Body {
+                content_type: first.content_type,
+                inner: inner.freeze(),
+            }
+        }
+    }
+
+    /// Iterator returned by [`Body::lines`]
+    #[derive(Debug, Clone)]
+    pub struct Lines {
+        rest: Bytes,
+    }
+
+    impl Iterator for Lines {
+        type Item = Result<String, crate::Error>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.rest.is_empty() {
+                return None;
+            }
+            let line = match self.rest.iter().position(|byte| *byte == b'\n') {
+                Some(end) => {
+                    let line = self.rest.split_to(end + 1);
+                    line.slice(..end)
+                }
+                None => self.rest.split_off(0),
+            };
+            let line = line.strip_suffix(b"\r").unwrap_or(&line);
+            Some(String::from_utf8(line.to_vec()).map_err(|_| crate::Error::InvalidBody))
+        }
     }
 }
 
@@ -164,35 +379,81 @@ impl TryFrom<Request> for ::http::Request<body::Body> {
             .iter()
             .fold(builder, |builder, (k, v)| builder.header(k, v));
 
-        let body = req.body.map_or_else(body::Body::empty, body::Body::from);
+        let builder = if req.body.is_none() {
+            builder.extension(crate::http::NoBody)
+        } else {
+            builder
+        };
+
+        let content_type = req
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| value.to_owned());
+        let mut body = req.body.map_or_else(body::Body::empty, body::Body::from);
+        if let Some(content_type) = content_type {
+            body.content_type = content_type;
+        }
         builder.body(body).map_err(|_| Error::InvalidBody)
     }
 }
 
 impl From<::http::Response<body::Body>> for Response {
     fn from(res: ::http::Response<body::Body>) -> Self {
-        let status = res.status().as_u16();
-        let headers = if !res.headers().is_empty() {
-            Some(
-                res.headers()
-                    .iter()
-                    .map(|(name, value)| (name.to_string(), value.to_str().unwrap().to_string()))
-                    .collect::<Vec<(String, String)>>(),
-            )
-        } else {
-            None
-        };
-
-        let body = Some(res.into_body().to_vec());
+        let (parts, body) = res.into_parts();
+        let mut headers = crate::http::headers::to_bindgen(&parts.headers);
+        for (name, value) in headers.iter_mut() {
+            if name == ::http::header::CONTENT_TYPE.as_str() {
+                *value = with_utf8_charset(value);
+            }
+        }
+        // always send a definitive length so clients don't wait for the connection to close
+        let body_allowed = !(parts.status.is_informational()
+            || parts.status == ::http::StatusCode::NO_CONTENT
+            || parts.status == ::http::StatusCode::NOT_MODIFIED);
+        if body_allowed
+            && !parts.headers.contains_key(::http::header::CONTENT_LENGTH)
+            && !parts
+                .headers
+                .contains_key(::http::header::TRANSFER_ENCODING)
+        {
+            headers.push((
+                ::http::header::CONTENT_LENGTH.to_string(),
+                body.len().to_string(),
+            ));
+        }
 
         Response {
-            status,
-            headers,
-            body,
+            status: parts.status.as_u16(),
+            headers: if headers.is_empty() {
+                None
+            } else {
+                Some(headers)
+            },
+            body: Some(body.to_vec()),
         }
     }
 }
 
+/// append `; charset=utf-8` to a textual content type without a charset so browsers don't guess
+fn with_utf8_charset(content_type: &str) -> String {
+    let Ok(mime) = content_type.parse::<mime::Mime>() else {
+        return content_type.to_string();
+    };
+    let textual = mime.type_() == mime::TEXT
+        || mime.suffix() == Some(mime::JSON)
+        || mime.suffix() == Some(mime::XML)
+        || (mime.type_() == mime::APPLICATION && matches!(mime.subtype().as_str(), "json" | "xml"));
+    if textual && mime.get_param(mime::CHARSET).is_none() {
+        format!(
+            "{}; charset=utf-8",
+            content_type.trim_end().trim_end_matches(';')
+        )
+    } else {
+        content_type.to_string()
+    }
+}
+
 impl TryFrom<Response> for ::http::Response<body::Body> {
     type Error = Error;
 
@@ -213,3 +474,295 @@ impl TryFrom<Response> for ::http::Response<body::Body> {
         builder.body(body).map_err(|_| Error::InvalidBody)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ::http::StatusCode;
+
+    use super::*;
+
+    fn http_error() -> Error {
+        let error = ::http::Request::builder().uri("\n").body(()).unwrap_err();
+        Error::HttpError(error)
+    }
+
+    #[test]
+    fn status_hint() {
+        assert_eq!(
+            Error::UnsupportedMethod(::http::Method::CONNECT).status_hint(),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+        assert_eq!(
+            Error::BindgenHttpError(HttpError::TooManyRequests).status_hint(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            Error::BindgenHttpError(HttpError::RequestError).status_hint(),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            Error::BindgenHttpError(HttpError::RuntimeError).status_hint(),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            Error::BindgenHttpError(HttpError::InvalidUrl).status_hint(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            Error::BindgenHttpError(HttpError::DestinationNotAllowed).status_hint(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            http_error().status_hint(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            Error::InvalidBody.status_hint(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            Error::InvalidStatusCode(1000).status_hint(),
+            StatusCode::BAD_GATEWAY
+        );
+    }
+
+    #[test]
+    fn is_retryable() {
+        assert!(Error::BindgenHttpError(HttpError::RequestError).is_retryable());
+        assert!(Error::BindgenHttpError(HttpError::RuntimeError).is_retryable());
+        assert!(Error::BindgenHttpError(HttpError::TooManyRequests).is_retryable());
+
+        assert!(!Error::BindgenHttpError(HttpError::DestinationNotAllowed).is_retryable());
+        assert!(!Error::BindgenHttpError(HttpError::InvalidUrl).is_retryable());
+        assert!(!Error::UnsupportedMethod(::http::Method::CONNECT).is_retryable());
+        assert!(!http_error().is_retryable());
+        assert!(!Error::InvalidBody.is_retryable());
+        assert!(!Error::InvalidStatusCode(1000).is_retryable());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn serialization_error() {
+        let error = Error::from(serde_json::from_str::<serde_json::Value>("{").unwrap_err());
+        assert_eq!(error.status_hint(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn response_too_large_error() {
+        let error = Error::ResponseTooLarge(10);
+        assert_eq!(error.status_hint(), StatusCode::BAD_GATEWAY);
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn redirect_errors() {
+        let error = Error::RedirectLoop("http://a/".to_string());
+        assert_eq!(error.status_hint(), StatusCode::BAD_GATEWAY);
+        assert!(!error.is_retryable());
+        let error = Error::TooManyRedirects(5);
+        assert_eq!(error.status_hint(), StatusCode::BAD_GATEWAY);
+        assert!(!error.is_retryable());
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn digest_mismatch_error() {
+        assert_eq!(Error::DigestMismatch.status_hint(), StatusCode::BAD_GATEWAY);
+        assert!(!Error::DigestMismatch.is_retryable());
+    }
+
+    #[test]
+    fn timeout_error() {
+        assert_eq!(Error::Timeout.status_hint(), StatusCode::GATEWAY_TIMEOUT);
+        assert!(Error::Timeout.is_retryable());
+    }
+
+    #[test]
+    fn body_concat() {
+        let body = body::Body::concat([
+            body::Body::from("<p>").with_content_type("text/html"),
+            body::Body::from("hi"),
+            body::Body::from("</p>"),
+        ]);
+        assert_eq!(body.as_ref(), b"<p>hi</p>");
+        assert_eq!(body.content_type(), "text/html");
+        assert!(body::Body::concat([]).is_empty());
+    }
+
+    #[test]
+    fn body_slice() {
+        let body = body::Body::from("0123456789").with_content_type("text/csv");
+        let slice = body.slice(2..5);
+        assert_eq!(slice.as_ref(), b"234");
+        assert_eq!(slice.content_type(), "text/csv");
+        assert_eq!(body.slice(..=1).as_ref(), b"01");
+        assert_eq!(body.slice(8..).as_ref(), b"89");
+        let (start, end) = (5, 2);
+        assert!(body.slice(start..end).is_empty());
+        assert!(body.slice(5..20).is_empty());
+    }
+
+    #[test]
+    fn body_from_bytes() {
+        let bytes = bytes::Bytes::from_static(b"shared");
+        let body = body::Body::from(bytes.clone());
+        assert_eq!(body.as_ptr(), bytes.as_ptr());
+        let body = body::Body::from(bytes::BytesMut::from(&b"mut"[..]));
+        assert_eq!(body.as_ref(), b"mut");
+    }
+
+    #[test]
+    fn body_from_fmt() {
+        let body = body::Body::from_fmt(format_args!("len = {}", 3));
+        assert_eq!(body.as_ref(), b"len = 3");
+        assert_eq!(body.content_type(), "text/plain; charset=utf-8");
+        assert_eq!(
+            body::Body::from_fmt(format_args!("static")).as_ref(),
+            b"static"
+        );
+    }
+
+    fn host_request(body: Option<&[u8]>, headers: &[(&str, &str)]) -> ::http::Request<body::Body> {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        ::http::Request::try_from(Request {
+            method: Method::Post,
+            uri: "/".to_string(),
+            headers,
+            body: body.map(<[u8]>::to_vec),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn missing_body() {
+        let req = host_request(None, &[]);
+        assert!(req.extensions().get::<crate::http::NoBody>().is_some());
+        assert!(req.body().is_empty());
+        let req = host_request(Some(b""), &[]);
+        assert!(req.extensions().get::<crate::http::NoBody>().is_none());
+    }
+
+    #[test]
+    fn body_reader() {
+        use std::io::Read;
+
+        let body = body::Body::from("hello");
+        let mut text = String::new();
+        body.reader().read_to_string(&mut text).unwrap();
+        assert_eq!(text, "hello");
+        assert_eq!(body.as_ref(), b"hello");
+    }
+
+    #[test]
+    fn inbound_content_type() {
+        let req = host_request(Some(b"{}"), &[("Content-Type", "application/json")]);
+        assert_eq!(req.body().content_type(), "application/json");
+        let req = host_request(Some(b"{}"), &[]);
+        assert_eq!(
+            req.body().content_type(),
+            body::Body::from(b"{}".to_vec()).content_type()
+        );
+    }
+
+    #[test]
+    fn body_mime() {
+        let body = body::Body::from("{}").with_content_type("application/json; charset=utf-8");
+        let mime = body.mime().unwrap();
+        assert_eq!(mime.essence_str(), "application/json");
+        assert_eq!(mime.get_param(mime::CHARSET), Some(mime::UTF_8));
+        assert!(body.with_content_type("not a mime").mime().is_none());
+    }
+
+    #[test]
+    fn body_lines() {
+        let body = body::Body::from(&b"{\"a\":1}\r\n\n{\"b\":2}\n\xff\nlast"[..]);
+        let lines = body.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0].as_deref().ok(), Some("{\"a\":1}"));
+        assert_eq!(lines[1].as_deref().ok(), Some(""));
+        assert_eq!(lines[2].as_deref().ok(), Some("{\"b\":2}"));
+        assert!(matches!(lines[3], Err(Error::InvalidBody)));
+        assert_eq!(lines[4].as_deref().ok(), Some("last"));
+        assert_eq!(body::Body::empty().lines().count(), 0);
+    }
+
+    fn host_response(res: ::http::Response<body::Body>) -> Response {
+        Response::from(res)
+    }
+
+    fn header<'a>(res: &'a Response, name: &str) -> Option<&'a str> {
+        res.headers
+            .iter()
+            .flatten()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn utf8_charset() {
+        assert_eq!(with_utf8_charset("text/html"), "text/html; charset=utf-8");
+        assert_eq!(
+            with_utf8_charset("application/json;"),
+            "application/json; charset=utf-8"
+        );
+        assert_eq!(
+            with_utf8_charset("application/problem+json"),
+            "application/problem+json; charset=utf-8"
+        );
+        assert_eq!(
+            with_utf8_charset("text/plain; charset=iso-8859-1"),
+            "text/plain; charset=iso-8859-1"
+        );
+        assert_eq!(with_utf8_charset("image/png"), "image/png");
+        assert_eq!(with_utf8_charset("not a mime"), "not a mime");
+
+        let res = ::http::Response::builder()
+            .header("content-type", "text/csv")
+            .body(body::Body::empty())
+            .unwrap();
+        assert_eq!(
+            header(&host_response(res), "content-type"),
+            Some("text/csv; charset=utf-8")
+        );
+    }
+
+    #[test]
+    fn content_length_framing() {
+        let res = host_response(::http::Response::new(body::Body::from("hello")));
+        assert_eq!(header(&res, "content-length"), Some("5"));
+
+        let res = host_response(::http::Response::new(body::Body::empty()));
+        assert_eq!(header(&res, "content-length"), Some("0"));
+
+        for status in [StatusCode::NO_CONTENT, StatusCode::NOT_MODIFIED] {
+            let mut res = ::http::Response::new(body::Body::empty());
+            *res.status_mut() = status;
+            assert_eq!(header(&host_response(res), "content-length"), None);
+        }
+
+        let res = ::http::Response::builder()
+            .header("content-length", "100")
+            .body(body::Body::from("head"))
+            .unwrap();
+        let res = host_response(res);
+        assert_eq!(
+            res.headers
+                .iter()
+                .flatten()
+                .filter(|(name, _)| name == "content-length")
+                .count(),
+            1
+        );
+        assert_eq!(header(&res, "content-length"), Some("100"));
+
+        let res = ::http::Response::builder()
+            .header("transfer-encoding", "chunked")
+            .body(body::Body::from("data"))
+            .unwrap();
+        assert_eq!(header(&host_response(res), "content-length"), None);
+    }
+}
@@ -0,0 +1,58 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+//! Values are read with the WASI `random_get` call, which the FastEdge runtime serves from the
+//! host cryptographically secure generator, so they are suitable for request IDs, nonces and
+//! CSRF tokens. Host builds, e.g. handler unit tests, read the operating system generator instead.
+
+/// Return `len` random bytes
+pub fn bytes(len: usize) -> Vec<u8> {
+    let mut buf = vec![0; len];
+    fill(&mut buf);
+    buf
+}
+
+/// Return a random `u64`
+pub fn u64() -> u64 {
+    let mut buf = [0; 8];
+    fill(&mut buf);
+    u64::from_ne_bytes(buf)
+}
+
+/// Fill `buf` with random bytes
+pub fn fill(buf: &mut [u8]) {
+    if buf.is_empty() {
+        return;
+    }
+    #[cfg(target_arch = "wasm32")]
+    // SAFETY: the pointer and length describe `buf`, which is valid for writes
+    if let Err(errno) = unsafe { wasi::random_get(buf.as_mut_ptr(), buf.len()) } {
+        panic!("host random_get failed: {}", errno);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(error) = getrandom::getrandom(buf) {
+        panic!("OS random generator failed: {}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_buffer() {
+        assert!(bytes(0).is_empty());
+        assert_eq!(bytes(32).len(), 32);
+
+        // 2^-256 chance of a false failure
+        let mut buf = [0; 32];
+        fill(&mut buf);
+        assert_ne!(buf, [0; 32]);
+    }
+
+    #[test]
+    fn values_differ() {
+        assert_ne!(bytes(16), bytes(16));
+        assert_ne!(u64(), u64());
+    }
+}
@@ -0,0 +1,180 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+//! Helpers for unit testing `#[fastedge::http]` handlers on the host, enabled with the `testing`
+//! feature. They panic on invalid input, which is the expected behavior inside tests.
+use std::fmt::Display;
+
+use ::http::{request, Method, Request, Response};
+
+use crate::body::Body;
+use crate::gcore::fastedge::http::Method as HostMethod;
+use crate::http::RequestExt;
+use crate::http_handler::{Guest, Request as HostRequest};
+
+/// Builder for handler test requests
+#[derive(Debug)]
+pub struct TestRequest {
+    builder: request::Builder,
+    body: Option<Body>,
+}
+
+impl TestRequest {
+    /// Request with the given method and URI
+    pub fn new(method: Method, uri: &str) -> Self {
+        Self {
+            builder: Request::builder().method(method).uri(uri),
+            body: None,
+        }
+    }
+
+    /// `GET` request
+    pub fn get(uri: &str) -> Self {
+        Self::new(Method::GET, uri)
+    }
+
+    /// `POST` request
+    pub fn post(uri: &str) -> Self {
+        Self::new(Method::POST, uri)
+    }
+
+    /// `PUT` request
+    pub fn put(uri: &str) -> Self {
+        Self::new(Method::PUT, uri)
+    }
+
+    /// `DELETE` request
+    pub fn delete(uri: &str) -> Self {
+        Self::new(Method::DELETE, uri)
+    }
+
+    /// Append a request header
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.builder = self.builder.header(name, value);
+        self
+    }
+
+    /// Set request body, without it the request is built as received with no body
+    pub fn body(mut self, body: impl Into<Body>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Set a JSON request body and `Content-Type: application/json`
+    #[cfg(feature = "json")]
+    pub fn json_body<T: serde::Serialize + ?Sized>(mut self, value: &T) -> Self {
+        let json = serde_json::to_vec(value).expect("test request body serialization");
+        self.builder = self.builder.header(
+            ::http::header::CONTENT_TYPE,
+            mime::APPLICATION_JSON.as_ref(),
+        );
+        self.body = Some(Body {
+            content_type: mime::APPLICATION_JSON.to_string(),
+            inner: json.into(),
+        });
+        self
+    }
+
+    /// Build the request
+    pub fn build(self) -> Request<Body> {
+        let (builder, body) = match self.body {
+            Some(body) => (self.builder, body),
+            None => (self.builder.extension(crate::http::NoBody), Body::empty()),
+        };
+        builder.body(body).expect("valid test request")
+    }
+}
+
+/// Invoke a handler function directly, an error is turned into a `500 Internal Server Error`
+/// plain text response with the error message as body.
+///
+/// The macro is bypassed, so its options, the status of response-producing errors and the
+/// conversion to the host response (e.g. `Content-Length`, `charset`) are not applied; use
+/// [`process`] to test the handler exactly as deployed.
+pub fn run<F, E>(handler: F, req: Request<Body>) -> Response<Body>
+where
+    F: FnOnce(Request<Body>) -> Result<Response<Body>, E>,
+    E: Display,
+{
+    handler(req).unwrap_or_else(|error| crate::http::internal_error(error.to_string()))
+}
+
+/// Send a request through the `Component` generated by `#[fastedge::http]`, the same code path
+/// the runtime invokes, e.g. `process::<Component>(TestRequest::get("/").build())`.
+///
+/// The request is converted to the host representation and the host response back, so macro
+/// options, error responses and response framing are all applied. Panics if the method can't be
+/// delivered by the host.
+pub fn process<G: Guest>(req: Request<Body>) -> Response<Body> {
+    let has_body = req.has_body();
+    let (parts, body) = req.into_parts();
+    let method = match parts.method {
+        Method::GET => HostMethod::Get,
+        Method::POST => HostMethod::Post,
+        Method::PUT => HostMethod::Put,
+        Method::DELETE => HostMethod::Delete,
+        Method::HEAD => HostMethod::Head,
+        Method::PATCH => HostMethod::Patch,
+        Method::OPTIONS => HostMethod::Options,
+        method => panic!("method `{}` is not delivered by the host", method),
+    };
+    let req = HostRequest {
+        method,
+        uri: parts.uri.to_string(),
+        headers: crate::http::headers::to_bindgen(&parts.headers),
+        body: has_body.then(|| body.to_vec()),
+    };
+    Response::try_from(G::process(req)).expect("valid handler response")
+}
+
+#[cfg(test)]
+mod tests {
+    use ::http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn build_request() {
+        let req = TestRequest::post("http://app/items?id=1")
+            .header("x-test", "1")
+            .body("data")
+            .build();
+        assert_eq!(req.method(), Method::POST);
+        assert_eq!(req.uri().query(), Some("id=1"));
+        assert_eq!(req.headers()["x-test"], "1");
+        assert_eq!(&req.body()[..], b"data");
+        assert!(req.has_body());
+
+        assert!(!TestRequest::get("/").build().has_body());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_body() {
+        let req = TestRequest::put("/")
+            .json_body(&serde_json::json!({"a": 1}))
+            .build();
+        assert_eq!(
+            req.headers()[::http::header::CONTENT_TYPE],
+            "application/json"
+        );
+        assert_eq!(&req.body()[..], br#"{"a":1}"#);
+    }
+
+    #[test]
+    fn run_handler() {
+        fn handler(req: Request<Body>) -> Result<Response<Body>, String> {
+            match req.uri().path() {
+                "/" => Ok(Response::new(Body::from("ok"))),
+                _ => Err("not here".to_string()),
+            }
+        }
+
+        let res = run(handler, TestRequest::get("/").build());
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = run(handler, TestRequest::delete("/other").build());
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(&res.body()[..], b"not here");
+    }
+}
@@ -0,0 +1,244 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Current wall clock time provided by the host
+pub fn now() -> SystemTime {
+    SystemTime::now()
+}
+
+/// Point in time by which work must finish, e.g. the total budget of a handler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Deadline {
+    at: Instant,
+}
+
+impl Deadline {
+    /// Deadline `budget` from now
+    pub fn after(budget: Duration) -> Self {
+        Self {
+            at: Instant::now() + budget,
+        }
+    }
+
+    /// Time left until the deadline, zero once it has passed
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    /// Whether the deadline has passed
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+}
+
+/// Format time as an HTTP-date (RFC 9110), e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+///
+/// Returns `None` for times before 1970 or after 9999, which have no HTTP-date.
+pub fn format_http_date(time: SystemTime) -> Option<String> {
+    let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let days = secs / 86400;
+    let (hours, minutes, seconds) = (secs % 86400 / 3600, secs % 3600 / 60, secs % 60);
+    // 1970-01-01 was a Thursday
+    let weekday = WEEKDAYS[((days + 4) % 7) as usize];
+    let (year, month, day) = civil_from_days(i64::try_from(days).ok()?);
+    if year > 9999 {
+        return None;
+    }
+
+    Some(format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        weekday,
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        hours,
+        minutes,
+        seconds
+    ))
+}
+
+/// Parse an HTTP-date (RFC 9110) in the preferred IMF-fixdate format or one of the obsolete
+/// RFC 850 and asctime formats. The weekday is not checked, years must have four digits and
+/// dates before 1970 are rejected.
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts = value.split_whitespace().collect::<Vec<&str>>();
+    let (day, month, year, time) = match parts.as_slice() {
+        // Sun, 06 Nov 1994 08:49:37 GMT
+        [_, day, month, year, time, "GMT"] => (*day, *month, four_digit_year(year)?, *time),
+        // Sunday, 06-Nov-94 08:49:37 GMT
+        [_, date, time, "GMT"] => {
+            let mut date = date.split('-');
+            let (day, month, year) = (date.next()?, date.next()?, date.next()?);
+            if year.len() != 2 || date.next().is_some() {
+                return None;
+            }
+            let year = year.parse::<i64>().ok()?;
+            let year = if year < 70 { 2000 + year } else { 1900 + year };
+            (day, month, year, *time)
+        }
+        // Sun Nov  6 08:49:37 1994
+        [_, month, day, time, year] => (*day, *month, four_digit_year(year)?, *time),
+        _ => return None,
+    };
+    let month = MONTHS.iter().position(|name| *name == month)? as i64 + 1;
+    let day = day
+        .parse::<i64>()
+        .ok()
+        .filter(|day| (1..=days_in_month(year, month)).contains(day))?;
+
+    let mut time = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+    if time.next().is_some() || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    let secs = days
+        .checked_mul(86400)?
+        .checked_add(hours * 3600 + minutes * 60 + seconds)?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+fn four_digit_year(year: &str) -> Option<i64> {
+    if year.len() != 4 || !year.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    year.parse().ok()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Convert a proleptic Gregorian date to days since the Unix epoch, inverse of [`civil_from_days`]
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Convert days since the Unix epoch to a (year, month, day) proleptic Gregorian date
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn format_date() {
+        let time = UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(
+            format_http_date(time).unwrap(),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert_eq!(
+            format_http_date(UNIX_EPOCH).unwrap(),
+            "Thu, 01 Jan 1970 00:00:00 GMT"
+        );
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951782400);
+        assert_eq!(
+            format_http_date(leap_day).unwrap(),
+            "Tue, 29 Feb 2000 00:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn format_out_of_range() {
+        assert_eq!(format_http_date(UNIX_EPOCH - Duration::from_secs(1)), None);
+        let year_10000 = UNIX_EPOCH + Duration::from_secs(253402300800);
+        assert_eq!(format_http_date(year_10000), None);
+    }
+
+    #[test]
+    fn deadline() {
+        let deadline = Deadline::after(Duration::from_secs(60));
+        assert!(!deadline.is_expired());
+        assert!(deadline.remaining() > Duration::from_secs(59));
+        let expired = Deadline::after(Duration::ZERO);
+        assert!(expired.is_expired());
+        assert_eq!(expired.remaining(), Duration::ZERO);
+        assert!(expired < deadline);
+    }
+
+    #[test]
+    fn parse_date_formats() {
+        let expected = Some(UNIX_EPOCH + Duration::from_secs(784111777));
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), expected);
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), expected);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), expected);
+        assert_eq!(
+            parse_http_date("Thursday, 01-Jan-26 00:00:00 GMT"),
+            parse_http_date("Thu, 01 Jan 2026 00:00:00 GMT")
+        );
+    }
+
+    #[test]
+    fn parse_leap_day() {
+        assert_eq!(
+            parse_http_date("Tue, 29 Feb 2000 00:00:00 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(951782400))
+        );
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            parse_http_date(&format_http_date(time).unwrap()),
+            Some(time)
+        );
+    }
+
+    #[test]
+    fn parse_invalid_dates() {
+        for value in [
+            "",
+            "Sun, 06 Nov 1994 08:49:37 UTC",
+            "Sun, 06 Foo 1994 08:49:37 GMT",
+            "Sun, 32 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 24:00:00 GMT",
+            "Sun, 06 Nov 1994 08:49 GMT",
+            "Sun, 06 Nov 1994 08:49:37:00 GMT",
+            "Wed, 31 Dec 1969 23:59:59 GMT",
+            "Sunday, 06-Nov-1994 08:49:37 GMT",
+            "Sun, 06 Nov 400000000000 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 400000000000",
+            "Sun, 06 Nov 994 08:49:37 GMT",
+            "Sun, 06 Nov +994 08:49:37 GMT",
+            "Thu, 31 Feb 1994 08:49:37 GMT",
+            "Sat, 29 Feb 1900 00:00:00 GMT",
+            "Thu, 31 Apr 2026 00:00:00 GMT",
+        ] {
+            assert_eq!(parse_http_date(value), None, "{}", value);
+        }
+    }
+}
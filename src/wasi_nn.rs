@@ -0,0 +1,633 @@
+/*
+* Copyright 2024 G-Core Innovations SARL
+*/
+#![allow(missing_docs)]
+wit_bindgen::generate!({
+    world: "ml",
+    path: "wasi-nn/wit"
+});
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+#[cfg(test)]
+use self::mock::{graph, inference};
+use self::wasi::nn::errors::Error;
+use self::wasi::nn::tensor::{Tensor, TensorType};
+#[cfg(not(test))]
+use self::wasi::nn::{graph, inference};
+
+/// Channel order of image input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorOrder {
+    /// Red, green, blue
+    #[default]
+    Rgb,
+    /// Blue, green, red, e.g. for models trained with OpenCV
+    Bgr,
+}
+
+/// Position of the channel dimension in an image input tensor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// `[batch, channels, height, width]`, one plane per channel
+    #[default]
+    Nchw,
+    /// `[batch, height, width, channels]`, channels interleaved per pixel
+    Nhwc,
+}
+
+/// Input tensor layout expected by a model
+#[derive(Debug, Clone)]
+pub struct InputSpec {
+    /// Tensor dimensions, e.g. `[1, 3, 224, 224]`
+    pub dimensions: Vec<u32>,
+    /// Tensor element type
+    pub tensor_type: TensorType,
+    /// Channel order of image input
+    pub color_order: ColorOrder,
+    /// Channel dimension position of image input
+    pub layout: Layout,
+    /// Per-channel mean subtracted from 0-255 pixel values of image input, in `color_order`
+    pub mean: [f32; 3],
+    /// Per-channel divisor applied after `mean` to pixel values of image input, in `color_order`
+    pub std: [f32; 3],
+}
+
+impl Default for InputSpec {
+    /// Empty dimensions, `FP32` and raw 0-255 RGB values in NCHW layout
+    fn default() -> Self {
+        Self {
+            dimensions: vec![],
+            tensor_type: TensorType::Fp32,
+            color_order: ColorOrder::default(),
+            layout: Layout::default(),
+            mean: [0.0; 3],
+            std: [1.0; 3],
+        }
+    }
+}
+
+impl InputSpec {
+    /// tensor size in bytes
+    fn sample_size(&self) -> usize {
+        let elements = self
+            .dimensions
+            .iter()
+            .map(|dim| *dim as usize)
+            .product::<usize>();
+        elements * element_size(self.tensor_type)
+    }
+
+    /// image width and height of a single-sample 3-channel input
+    #[cfg(feature = "image")]
+    fn image_size(&self) -> Option<(u32, u32)> {
+        let (channels, height, width) = match (self.layout, self.dimensions.as_slice()) {
+            (Layout::Nchw, [1, channels, height, width]) => (channels, height, width),
+            (Layout::Nhwc, [1, height, width, channels]) => (channels, height, width),
+            _ => return None,
+        };
+        (*channels == 3 && *width > 0 && *height > 0).then_some((*width, *height))
+    }
+}
+
+/// Decode an image and convert it to input tensor bytes for `spec`, enabled with the `image`
+/// feature.
+///
+/// The image is resized to the width and height in `spec.dimensions`, which must describe a
+/// single 3-channel sample, its channels are ordered by `spec.color_order` and laid out by
+/// `spec.layout`, and `(value - mean) / std` is stored as `spec.tensor_type`. Integer types
+/// are rounded and saturated. Input that can't be decoded, or a spec that doesn't describe an
+/// image, is an [`Error::InvalidArgument`]; `FP16` and `BF16` are an
+/// [`Error::UnsupportedOperation`].
+#[cfg(feature = "image")]
+pub fn image_to_tensor(image: &[u8], spec: &InputSpec) -> Result<Vec<u8>, Error> {
+    let (width, height) = spec.image_size().ok_or(Error::InvalidArgument)?;
+    if matches!(spec.tensor_type, TensorType::Fp16 | TensorType::Bf16) {
+        return Err(Error::UnsupportedOperation);
+    }
+    let image = ::image::load_from_memory(image)
+        .map_err(|_| Error::InvalidArgument)?
+        .resize_exact(width, height, ::image::imageops::FilterType::Triangle)
+        .to_rgb8();
+
+    let channel_order = match spec.color_order {
+        ColorOrder::Rgb => [0, 1, 2],
+        ColorOrder::Bgr => [2, 1, 0],
+    };
+    let pixels = (width * height) as usize;
+    let mut values = vec![0.0f32; pixels * 3];
+    for (index, pixel) in image.pixels().enumerate() {
+        for (channel, source) in channel_order.iter().enumerate() {
+            let value = (f32::from(pixel.0[*source]) - spec.mean[channel]) / spec.std[channel];
+            let position = match spec.layout {
+                Layout::Nchw => channel * pixels + index,
+                Layout::Nhwc => index * 3 + channel,
+            };
+            values[position] = value;
+        }
+    }
+
+    let mut data = Vec::with_capacity(values.len() * element_size(spec.tensor_type));
+    for value in values {
+        match spec.tensor_type {
+            TensorType::Fp32 => data.extend_from_slice(&value.to_le_bytes()),
+            TensorType::Fp64 => data.extend_from_slice(&f64::from(value).to_le_bytes()),
+            TensorType::U8 => data.push(value.round() as u8),
+            TensorType::I32 => data.extend_from_slice(&(value.round() as i32).to_le_bytes()),
+            TensorType::I64 => data.extend_from_slice(&(value.round() as i64).to_le_bytes()),
+            TensorType::Fp16 | TensorType::Bf16 => unreachable!("rejected above"),
+        }
+    }
+    Ok(data)
+}
+
+/// Classify an image with a model loaded by the runtime, enabled with the `image` feature.
+///
+/// The image is converted with [`image_to_tensor`] and run with [`classify_tensor`].
+#[cfg(feature = "image")]
+pub fn classify(
+    model_name: &str,
+    image: &[u8],
+    spec: &InputSpec,
+) -> Result<Vec<(usize, f32)>, Error> {
+    classify_tensor(model_name, image_to_tensor(image, spec)?, spec)
+}
+
+/// Run a model loaded by the runtime on prepared input tensor bytes.
+///
+/// The first output is read as `f32` probabilities indexed by class and returned as
+/// `(class index, probability)` pairs sorted by descending probability. Input that does not match
+/// `spec` fails with [`Error::InvalidArgument`].
+pub fn classify_tensor(
+    model_name: &str,
+    input: Vec<u8>,
+    spec: &InputSpec,
+) -> Result<Vec<(usize, f32)>, Error> {
+    if spec.sample_size() != input.len() {
+        return Err(Error::InvalidArgument);
+    }
+    let tensor = Tensor {
+        dimensions: spec.dimensions.clone(),
+        tensor_type: spec.tensor_type,
+        data: input,
+    };
+
+    let output = run(model_name, &tensor)?;
+    Ok(top_k(&output, output.len()))
+}
+
+/// Run a model on several samples in one batch and return the `f32` output of each sample.
+///
+/// `spec` describes a single sample with a leading batch dimension of 1, which is replaced by the
+/// number of inputs. Every input must match `spec`, otherwise [`Error::InvalidArgument`] is
+/// returned; an output that can't be split evenly between samples is an [`Error::RuntimeError`].
+pub fn infer_batch(
+    model_name: &str,
+    inputs: &[&[u8]],
+    spec: &InputSpec,
+) -> Result<Vec<Vec<f32>>, Error> {
+    if inputs.is_empty() {
+        return Ok(vec![]);
+    }
+    let sample_size = spec.sample_size();
+    if spec.dimensions.first() != Some(&1) || inputs.iter().any(|input| input.len() != sample_size)
+    {
+        return Err(Error::InvalidArgument);
+    }
+
+    let mut dimensions = spec.dimensions.clone();
+    dimensions[0] = u32::try_from(inputs.len()).map_err(|_| Error::InvalidArgument)?;
+    let tensor = Tensor {
+        dimensions,
+        tensor_type: spec.tensor_type,
+        data: inputs.concat(),
+    };
+
+    let output = run(model_name, &tensor)?;
+    if output.len() % inputs.len() != 0 {
+        return Err(Error::RuntimeError);
+    }
+    Ok(output
+        .chunks(output.len() / inputs.len())
+        .map(<[f32]>::to_vec)
+        .collect())
+}
+
+/// Softmax of raw model outputs (logits), the result sums to 1.0
+pub fn softmax(logits: &[f32]) -> Vec<f32> {
+    // shifting by the maximum keeps exp() from overflowing
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exp = logits
+        .iter()
+        .map(|logit| (logit - max).exp())
+        .collect::<Vec<f32>>();
+    let sum = exp.iter().sum::<f32>();
+    exp.into_iter().map(|value| value / sum).collect()
+}
+
+/// The `k` highest values as `(index, value)` pairs in descending order.
+///
+/// Only the top `k` are sorted, the rest of the output is partitioned without ordering.
+pub fn top_k(values: &[f32], k: usize) -> Vec<(usize, f32)> {
+    let mut indexed = values
+        .iter()
+        .copied()
+        .enumerate()
+        .collect::<Vec<(usize, f32)>>();
+    let k = k.min(indexed.len());
+    if k == 0 {
+        return vec![];
+    }
+    let descending = |a: &(usize, f32), b: &(usize, f32)| b.1.total_cmp(&a.1);
+    if k < indexed.len() {
+        indexed.select_nth_unstable_by(k - 1, descending);
+        indexed.truncate(k);
+    }
+    indexed.sort_by(descending);
+    indexed
+}
+
+/// run a named graph on one input tensor and decode the first output as little-endian `f32`
+fn run(model_name: &str, tensor: &Tensor) -> Result<Vec<f32>, Error> {
+    let output = Session::get(model_name)?.run(tensor)?;
+    if output.len() % 4 != 0 {
+        return Err(Error::RuntimeError);
+    }
+    Ok(output
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect())
+}
+
+thread_local! {
+    static SESSIONS: RefCell<HashMap<String, Session>> = RefCell::new(HashMap::new());
+}
+
+/// Loaded graph with its execution context, reused across inferences.
+///
+/// Sessions are cached for the lifetime of the app instance, which may serve several requests
+/// before the runtime recycles it; a new instance loads the graph again on first use. The
+/// [`classify_tensor`] and [`infer_batch`] helpers use it internally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    graph: graph::Graph,
+    context: inference::GraphExecutionContext,
+}
+
+impl Session {
+    /// Session of a model loaded by the runtime, the graph is loaded on the first call only
+    pub fn get(model_name: &str) -> Result<Session, Error> {
+        if let Some(session) = SESSIONS.with(|sessions| sessions.borrow().get(model_name).copied())
+        {
+            return Ok(session);
+        }
+        let graph = graph::load_by_name(model_name)?;
+        let context = inference::init_execution_context(graph)?;
+        let session = Session { graph, context };
+        SESSIONS.with(|sessions| {
+            sessions
+                .borrow_mut()
+                .insert(model_name.to_string(), session)
+        });
+        Ok(session)
+    }
+
+    /// Graph handle of the session
+    pub fn graph(&self) -> graph::Graph {
+        self.graph
+    }
+
+    /// Run the graph on one input tensor and return the raw bytes of the first output
+    pub fn run(&self, tensor: &Tensor) -> Result<Vec<u8>, Error> {
+        inference::set_input(self.context, 0, tensor)?;
+        inference::compute(self.context)?;
+        inference::get_output(self.context, 0)
+    }
+}
+
+/// Class labels indexed by model output position
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Labels {
+    names: Vec<String>,
+}
+
+impl Labels {
+    /// Parse newline-delimited labels, line `n` names class `n`
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let names = String::from_utf8_lossy(bytes)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .collect();
+        Self { names }
+    }
+
+    /// Label of a class index
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.names.get(index).map(String::as_str)
+    }
+
+    /// Number of labels
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether there are no labels
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// [`classify`] with class indices mapped to labels, classes without a label are dropped
+#[cfg(feature = "image")]
+pub fn classify_with_labels(
+    model_name: &str,
+    image: &[u8],
+    spec: &InputSpec,
+    labels: &Labels,
+) -> Result<Vec<(String, f32)>, Error> {
+    let results = classify(model_name, image, spec)?;
+    Ok(results
+        .into_iter()
+        .filter_map(|(index, probability)| {
+            labels
+                .get(index)
+                .map(|label| (label.to_string(), probability))
+        })
+        .collect())
+}
+
+fn element_size(tensor_type: TensorType) -> usize {
+    match tensor_type {
+        TensorType::U8 => 1,
+        TensorType::Fp16 | TensorType::Bf16 => 2,
+        TensorType::Fp32 | TensorType::I32 => 4,
+        TensorType::Fp64 | TensorType::I64 => 8,
+    }
+}
+
+/// In-memory stand-in for the host graph API, counting loads and returning preset logits
+#[cfg(test)]
+mod mock {
+    use std::cell::{Cell, RefCell};
+
+    use super::wasi::nn::tensor::Tensor;
+
+    thread_local! {
+        pub static LOADS: Cell<u32> = const { Cell::new(0) };
+        pub static OUTPUT: RefCell<Vec<f32>> = const { RefCell::new(vec![]) };
+        pub static INPUT: RefCell<Option<Tensor>> = const { RefCell::new(None) };
+    }
+
+    pub mod graph {
+        use super::super::wasi::nn::errors::Error;
+
+        pub type Graph = u32;
+
+        pub fn load_by_name(name: &str) -> Result<Graph, Error> {
+            if name == "missing" {
+                return Err(Error::NotFound);
+            }
+            super::LOADS.with(|loads| {
+                loads.set(loads.get() + 1);
+                Ok(loads.get())
+            })
+        }
+    }
+
+    pub mod inference {
+        use super::super::wasi::nn::errors::Error;
+        use super::super::wasi::nn::tensor::Tensor;
+        use super::graph::Graph;
+
+        pub type GraphExecutionContext = u32;
+
+        pub fn init_execution_context(graph: Graph) -> Result<GraphExecutionContext, Error> {
+            Ok(graph)
+        }
+
+        pub fn set_input(_: GraphExecutionContext, _: u32, tensor: &Tensor) -> Result<(), Error> {
+            super::INPUT.with(|input| *input.borrow_mut() = Some(tensor.clone()));
+            Ok(())
+        }
+
+        pub fn compute(_: GraphExecutionContext) -> Result<(), Error> {
+            Ok(())
+        }
+
+        pub fn get_output(_: GraphExecutionContext, _: u32) -> Result<Vec<u8>, Error> {
+            Ok(super::OUTPUT.with(|output| {
+                output
+                    .borrow()
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect()
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_output(logits: &[f32]) {
+        mock::OUTPUT.with(|output| *output.borrow_mut() = logits.to_vec());
+    }
+
+    fn input() -> Tensor {
+        mock::INPUT.with(|input| input.borrow().clone().expect("input set"))
+    }
+
+    fn spec(dimensions: Vec<u32>) -> InputSpec {
+        InputSpec {
+            dimensions,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn classify_tensor_sorted() {
+        set_output(&[0.1, 0.7, 0.2]);
+        let results = classify_tensor("model", vec![0; 8], &spec(vec![1, 2])).unwrap();
+        assert_eq!(results, vec![(1, 0.7), (2, 0.2), (0, 0.1)]);
+        assert_eq!(input().dimensions, vec![1, 2]);
+    }
+
+    #[test]
+    fn classify_tensor_size_mismatch() {
+        assert_eq!(
+            classify_tensor("model", vec![0; 7], &spec(vec![1, 2])),
+            Err(Error::InvalidArgument)
+        );
+        assert_eq!(
+            classify_tensor("missing", vec![0; 8], &spec(vec![1, 2])),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[test]
+    fn session_loaded_once() {
+        set_output(&[1.0]);
+        for _ in 0..3 {
+            classify_tensor("model", vec![0; 4], &spec(vec![1])).unwrap();
+        }
+        assert_eq!(mock::LOADS.with(|loads| loads.get()), 1);
+        assert_eq!(Session::get("model").unwrap().graph(), 1);
+
+        classify_tensor("other", vec![0; 4], &spec(vec![1])).unwrap();
+        assert_eq!(mock::LOADS.with(|loads| loads.get()), 2);
+    }
+
+    #[test]
+    fn batch_split_per_sample() {
+        set_output(&[0.1, 0.9, 0.8, 0.2]);
+        let first = [0u8; 8];
+        let second = [1u8; 8];
+        let outputs = infer_batch("model", &[&first, &second], &spec(vec![1, 2])).unwrap();
+        assert_eq!(outputs, vec![vec![0.1, 0.9], vec![0.8, 0.2]]);
+
+        let input = input();
+        assert_eq!(input.dimensions, vec![2, 2]);
+        assert_eq!(&input.data[..8], &first);
+        assert_eq!(&input.data[8..], &second);
+    }
+
+    #[test]
+    fn batch_validation() {
+        assert_eq!(infer_batch("model", &[], &spec(vec![1, 2])), Ok(vec![]));
+        assert_eq!(
+            infer_batch("model", &[&[0; 8], &[0; 4]], &spec(vec![1, 2])),
+            Err(Error::InvalidArgument)
+        );
+        assert_eq!(
+            infer_batch("model", &[&[0; 16]], &spec(vec![2, 2])),
+            Err(Error::InvalidArgument)
+        );
+
+        set_output(&[0.1, 0.2, 0.3]);
+        assert_eq!(
+            infer_batch("model", &[&[0; 8], &[0; 8]], &spec(vec![1, 2])),
+            Err(Error::RuntimeError)
+        );
+    }
+
+    #[test]
+    fn softmax_sums_to_one() {
+        let probabilities = softmax(&[1.0, 2.0, 3.0, 1000.0]);
+        assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        assert!(probabilities[3] > 0.99);
+        assert!(probabilities.iter().all(|p| p.is_finite()));
+    }
+
+    #[test]
+    fn top_k_highest_in_order() {
+        let values = [0.3, 0.9, 0.1, 0.5, 0.7];
+        assert_eq!(top_k(&values, 3), vec![(1, 0.9), (4, 0.7), (3, 0.5)]);
+        assert_eq!(top_k(&values, 10).len(), 5);
+        assert_eq!(top_k(&values, 0), vec![]);
+        assert_eq!(top_k(&[], 3), vec![]);
+    }
+
+    #[test]
+    fn labels() {
+        let labels = Labels::from_bytes(b"background\r\ncat\ndog\n");
+        assert_eq!(labels.len(), 3);
+        assert_eq!(labels.get(1), Some("cat"));
+        assert_eq!(labels.get(2), Some("dog"));
+        assert_eq!(labels.get(3), None);
+        assert!(Labels::from_bytes(b"").is_empty());
+    }
+
+    #[cfg(feature = "image")]
+    fn png(width: u32, height: u32, pixels: &[[u8; 3]]) -> Vec<u8> {
+        let raw = pixels.concat();
+        let image = ::image::RgbImage::from_raw(width, height, raw).unwrap();
+        let mut png = std::io::Cursor::new(vec![]);
+        image.write_to(&mut png, ::image::ImageFormat::Png).unwrap();
+        png.into_inner()
+    }
+
+    #[cfg(feature = "image")]
+    fn f32s(data: &[u8]) -> Vec<f32> {
+        data.chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_layout_and_color_order() {
+        let image = png(2, 1, &[[10, 20, 30], [40, 50, 60]]);
+
+        let nchw = image_to_tensor(&image, &spec(vec![1, 3, 1, 2])).unwrap();
+        assert_eq!(f32s(&nchw), vec![10.0, 40.0, 20.0, 50.0, 30.0, 60.0]);
+
+        let nhwc_bgr = InputSpec {
+            dimensions: vec![1, 1, 2, 3],
+            color_order: ColorOrder::Bgr,
+            layout: Layout::Nhwc,
+            ..Default::default()
+        };
+        let tensor = image_to_tensor(&image, &nhwc_bgr).unwrap();
+        assert_eq!(f32s(&tensor), vec![30.0, 20.0, 10.0, 60.0, 50.0, 40.0]);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_normalized() {
+        let image = png(1, 1, &[[255, 0, 128]]);
+        let normalized = InputSpec {
+            dimensions: vec![1, 3, 1, 1],
+            mean: [127.5, 0.0, 128.0],
+            std: [127.5, 1.0, 2.0],
+            ..Default::default()
+        };
+        let tensor = image_to_tensor(&image, &normalized).unwrap();
+        assert_eq!(f32s(&tensor), vec![1.0, 0.0, 0.0]);
+
+        let bytes = InputSpec {
+            dimensions: vec![1, 3, 1, 1],
+            tensor_type: TensorType::U8,
+            ..Default::default()
+        };
+        assert_eq!(image_to_tensor(&image, &bytes).unwrap(), vec![255, 0, 128]);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_resized_and_classified() {
+        set_output(&[0.25, 0.75]);
+        let image = png(4, 4, &[[1, 2, 3]; 16]);
+        let results = classify("model", &image, &spec(vec![1, 3, 2, 2])).unwrap();
+        assert_eq!(results, vec![(1, 0.75), (0, 0.25)]);
+        assert_eq!(input().data.len(), 3 * 2 * 2 * 4);
+
+        let labels = Labels::from_bytes(b"cat\n");
+        let named = classify_with_labels("model", &image, &spec(vec![1, 3, 2, 2]), &labels);
+        assert_eq!(named, Ok(vec![("cat".to_string(), 0.25)]));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_invalid_input() {
+        let image = png(1, 1, &[[0, 0, 0]]);
+        assert_eq!(
+            image_to_tensor(b"not an image", &spec(vec![1, 3, 1, 1])),
+            Err(Error::InvalidArgument)
+        );
+        assert_eq!(
+            image_to_tensor(&image, &spec(vec![1, 4, 1, 1])),
+            Err(Error::InvalidArgument)
+        );
+        let fp16 = InputSpec {
+            dimensions: vec![1, 3, 1, 1],
+            tensor_type: TensorType::Fp16,
+            ..Default::default()
+        };
+        assert_eq!(
+            image_to_tensor(&image, &fp16),
+            Err(Error::UnsupportedOperation)
+        );
+    }
+}
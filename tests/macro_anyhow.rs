@@ -0,0 +1,42 @@
+//! `#[fastedge::http]` with an `anyhow::Result` handler and the `anyhow` feature
+#![cfg(feature = "anyhow")]
+use anyhow::{anyhow, Context};
+use fastedge::body::Body;
+use fastedge::gcore::fastedge::http::Method;
+use fastedge::http::{HandlerError, Request, Response, StatusCode};
+use fastedge::http_handler::{Request as HostRequest, Response as HostResponse};
+
+#[allow(dead_code)]
+#[fastedge::http]
+fn main(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+    match req.uri().path() {
+        "/missing" => {
+            Err(HandlerError::new(StatusCode::NOT_FOUND, "no such thing")).context("lookup failed")
+        }
+        "/fail" => Err(anyhow!("boom")),
+        _ => Ok(Response::new(Body::from("ok"))),
+    }
+}
+
+fn get(uri: &str) -> HostResponse {
+    Component::process(HostRequest {
+        method: Method::Get,
+        uri: uri.to_string(),
+        headers: vec![],
+        body: None,
+    })
+}
+
+#[test]
+fn wrapped_handler_error_keeps_status() {
+    let res = get("http://app/missing");
+    assert_eq!(res.status, 404);
+    assert_eq!(res.body.as_deref(), Some(&b"no such thing"[..]));
+}
+
+#[test]
+fn opaque_error() {
+    let res = get("http://app/fail");
+    assert_eq!(res.status, 500);
+    assert_eq!(res.body.as_deref(), Some(&b"boom"[..]));
+}
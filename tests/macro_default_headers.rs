@@ -0,0 +1,59 @@
+//! `#[fastedge::http(default_headers(...))]` on handler and macro generated responses
+use fastedge::body::Body;
+use fastedge::gcore::fastedge::http::Method;
+use fastedge::http::{Request, Response};
+use fastedge::http_handler::{Request as HostRequest, Response as HostResponse};
+
+#[allow(dead_code)]
+#[fastedge::http(default_headers("x-content-type-options: nosniff", "server: fastedge"))]
+fn main(req: Request<Body>) -> Result<Response<Body>, Box<dyn std::error::Error>> {
+    match req.uri().path() {
+        "/fail" => Err("boom".into()),
+        _ => Ok(Response::builder()
+            .header("server", "handler")
+            .body(Body::from("ok"))?),
+    }
+}
+
+fn get(uri: &str) -> HostResponse {
+    Component::process(HostRequest {
+        method: Method::Get,
+        uri: uri.to_string(),
+        headers: vec![],
+        body: None,
+    })
+}
+
+fn header<'a>(res: &'a HostResponse, name: &str) -> Option<&'a str> {
+    res.headers
+        .iter()
+        .flatten()
+        .find(|(header, _)| header == name)
+        .map(|(_, value)| value.as_str())
+}
+
+#[test]
+fn handler_response() {
+    let res = get("http://app/");
+    assert_eq!(res.status, 200);
+    assert_eq!(header(&res, "x-content-type-options"), Some("nosniff"));
+    // set by the handler, not overridden
+    assert_eq!(header(&res, "server"), Some("handler"));
+}
+
+#[test]
+fn handler_error() {
+    let res = get("http://app/fail");
+    assert_eq!(res.status, 500);
+    assert_eq!(res.body.as_deref(), Some(&b"boom"[..]));
+    assert_eq!(header(&res, "x-content-type-options"), Some("nosniff"));
+    assert_eq!(header(&res, "server"), Some("fastedge"));
+}
+
+#[test]
+fn request_decode_error() {
+    let res = get("not a uri");
+    assert_eq!(res.status, 500);
+    assert_eq!(header(&res, "x-content-type-options"), Some("nosniff"));
+    assert_eq!(header(&res, "server"), Some("fastedge"));
+}
@@ -0,0 +1,31 @@
+//! `#[fastedge::http(error_format = "json")]`
+use fastedge::body::Body;
+use fastedge::gcore::fastedge::http::Method;
+use fastedge::http::{Request, Response};
+use fastedge::http_handler::{Request as HostRequest, Response as HostResponse};
+
+#[allow(dead_code)]
+#[fastedge::http(error_format = "json")]
+fn main(_req: Request<Body>) -> Result<Response<Body>, std::io::Error> {
+    Err(std::io::Error::other("no \"disk\""))
+}
+
+#[test]
+fn opaque_error_as_json() {
+    let res: HostResponse = Component::process(HostRequest {
+        method: Method::Get,
+        uri: "http://app/".to_string(),
+        headers: vec![],
+        body: None,
+    });
+    assert_eq!(res.status, 500);
+    assert_eq!(
+        res.body.as_deref(),
+        Some(&br#"{"error":"no \"disk\""}"#[..])
+    );
+    assert!(res
+        .headers
+        .iter()
+        .flatten()
+        .any(|(name, value)| name == "content-type" && value.starts_with("application/json")));
+}
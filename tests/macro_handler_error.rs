@@ -0,0 +1,55 @@
+//! `#[fastedge::http]` with a handler returning `HandlerError`
+use fastedge::body::Body;
+use fastedge::gcore::fastedge::http::Method;
+use fastedge::http::{HandlerError, Request, Response, StatusCode};
+use fastedge::http_handler::{Request as HostRequest, Response as HostResponse};
+
+#[allow(dead_code)]
+#[fastedge::http]
+fn main(req: Request<Body>) -> Result<Response<Body>, HandlerError> {
+    match req.uri().path() {
+        "/missing" => Err(HandlerError::new(StatusCode::NOT_FOUND, "no such thing")),
+        "/backend" => {
+            let backend = Request::builder()
+                .method("PROPFIND")
+                .uri("http://backend/")
+                .body(Body::empty())?;
+            Ok(fastedge::send_request(backend)?)
+        }
+        _ => Ok(Response::new(Body::from("ok"))),
+    }
+}
+
+fn get(uri: &str) -> HostResponse {
+    Component::process(HostRequest {
+        method: Method::Get,
+        uri: uri.to_string(),
+        headers: vec![],
+        body: None,
+    })
+}
+
+#[test]
+fn handler_error_keeps_status() {
+    let res = get("http://app/missing");
+    assert_eq!(res.status, 404);
+    assert_eq!(res.body.as_deref(), Some(&b"no such thing"[..]));
+}
+
+#[test]
+fn sdk_error_uses_status_hint() {
+    // PROPFIND can't be sent to a backend
+    let res = get("http://app/backend");
+    assert_eq!(res.status, 405);
+    assert_eq!(
+        res.body.as_deref(),
+        Some(&b"method `PROPFIND` is not supported"[..])
+    );
+}
+
+#[test]
+fn success() {
+    let res = get("http://app/");
+    assert_eq!(res.status, 200);
+    assert_eq!(res.body.as_deref(), Some(&b"ok"[..]));
+}